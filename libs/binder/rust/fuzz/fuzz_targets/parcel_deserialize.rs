@@ -0,0 +1,24 @@
+#![no_main]
+
+//! Feeds arbitrary byte buffers into a `Parcel` and drives the typed `read::<T>()` paths that a
+//! real transaction reply would go through, looking for panics, OOB reads, and integer-overflow
+//! in length prefixes. Every read is expected to return either `Ok` or a clean `StatusCode`
+//! error -- in particular, a malformed length prefix (negative, or larger than the remaining
+//! buffer) must produce `StatusCode::NOT_ENOUGH_DATA`/`BAD_VALUE` rather than allocating or
+//! indexing past the end.
+
+use binder::binder_impl::Parcel;
+use binder::{SpIBinder, Status};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut parcel = Parcel::from_bytes(data);
+
+    let _ = parcel.read::<bool>();
+    let _ = parcel.read::<i32>();
+    let _ = parcel.read::<Vec<u8>>();
+    let _ = parcel.read::<Option<String>>();
+    let _ = parcel.read::<Option<Vec<Option<String>>>>();
+    let _ = parcel.read::<Status>();
+    let _ = parcel.read::<SpIBinder>();
+});
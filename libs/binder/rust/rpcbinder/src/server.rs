@@ -128,6 +128,116 @@ impl RpcServer {
             ))
         }
     }
+
+    /// Creates a binder RPC server, serving the supplied binder service implementation on an
+    /// init-managed named Unix domain socket. Android init must already have created and bound
+    /// the listening socket for `name` (e.g. via a `socket` entry in a `.rc` file); this lets
+    /// services expose a stable filesystem-addressed RPC endpoint without manually creating and
+    /// binding the socket FD themselves.
+    pub fn new_unix_domain(mut service: SpIBinder, name: &str) -> Result<RpcServer, Error> {
+        let name = match CString::new(name) {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("Cannot convert {} to CString. Error: {:?}", name, e);
+                return Err(Error::from(ErrorKind::InvalidInput));
+            }
+        };
+        let service = service.as_native_mut();
+
+        // SAFETY: Service ownership is transferring to the server and won't be valid afterward.
+        // Plus the binder objects are threadsafe.
+        unsafe {
+            Self::checked_from_ptr(binder_rpc_unstable_bindgen::ARpcServer_newUnixDomain(
+                service,
+                name.as_ptr(),
+            ))
+        }
+    }
+
+    /// Creates a TLS-encrypted binder RPC server on the given IP address and port.
+    ///
+    /// The server generates (or reuses) a certificate at construction, which clients must pin via
+    /// [`RpcSessionRef::add_trusted_peer_certificate`] before connecting. Every accepted connection
+    /// is authenticated against that pinned trusted-certificate set, and unknown peers are rejected.
+    pub fn new_inet_tls(
+        mut service: SpIBinder,
+        address: &str,
+        port: u32,
+    ) -> Result<RpcServer, Error> {
+        let address = match CString::new(address) {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("Cannot convert {} to CString. Error: {:?}", address, e);
+                return Err(Error::from(ErrorKind::InvalidInput));
+            }
+        };
+        let service = service.as_native_mut();
+
+        // SAFETY: Service ownership is transferring to the server and won't be valid afterward.
+        // Plus the binder objects are threadsafe. The server picks the TLS
+        // `RpcTransportCtxFactory` internally and owns the generated certificate.
+        unsafe {
+            Self::checked_from_ptr(binder_rpc_unstable_bindgen::ARpcServer_newInetTls(
+                service,
+                address.as_ptr(),
+                port,
+            ))
+        }
+    }
+}
+
+/// Certificate encoding used when exchanging or pinning RPC TLS certificates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateFormat {
+    Pem,
+    Der,
+}
+
+impl From<CertificateFormat> for binder_rpc_unstable_bindgen::ARpcCertificateFormat {
+    fn from(format: CertificateFormat) -> Self {
+        match format {
+            CertificateFormat::Pem => binder_rpc_unstable_bindgen::ARpcCertificateFormat_PEM,
+            CertificateFormat::Der => binder_rpc_unstable_bindgen::ARpcCertificateFormat_DER,
+        }
+    }
+}
+
+#[cfg(not(target_os = "trusty"))]
+impl RpcServerRef {
+    /// Returns this server's certificate, in the given format, so that clients can pin it via
+    /// [`RpcSessionRef::add_trusted_peer_certificate`] before connecting over TLS.
+    pub fn certificate(&self, format: CertificateFormat) -> Result<Vec<u8>, Error> {
+        let mut len: size_t = 0;
+        // SAFETY: Passing a valid server pointer and an out-param for the length. A null data
+        // pointer requests just the length, which we use to size the real call below.
+        let needed = unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_getCertificate(
+                self.as_ptr(),
+                format.into(),
+                std::ptr::null_mut(),
+                &mut len,
+            )
+        };
+        if !needed {
+            return Err(Error::new(ErrorKind::Other, "Failed to query certificate size"));
+        }
+
+        let mut cert = vec![0u8; len];
+        // SAFETY: `cert` is a valid buffer of `len` bytes, matching the size we just queried.
+        let ok = unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_getCertificate(
+                self.as_ptr(),
+                format.into(),
+                cert.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        if ok {
+            Ok(cert)
+        } else {
+            Err(Error::new(ErrorKind::Other, "Failed to fetch certificate"))
+        }
+    }
 }
 
 #[cfg(target_os = "trusty")]
@@ -169,6 +279,7 @@ impl RpcServerRef {
         &self,
         modes: &[FileDescriptorTransportMode],
     ) {
+        let modes: Vec<_> = modes.iter().copied().map(Into::into).collect();
         // SAFETY: Does not keep the pointer after returning does, nor does it
         // read past its boundary. Only passes the 'self' pointer as an opaque handle.
         unsafe {
@@ -191,6 +302,77 @@ impl RpcServerRef {
             );
         }
     }
+
+    /// Sets a filter invoked for every incoming connection with the peer's raw socket address,
+    /// before any binder traffic is exchanged. Returning `false` drops the connection.
+    ///
+    /// This is the admission-control counterpart to
+    /// [`set_per_session_root_object`](Self::set_per_session_root_object): e.g. restricting a
+    /// vsock server to a CID allowlist, or rejecting non-loopback inet peers, without having to
+    /// construct a fresh server per accepted peer.
+    pub fn set_connection_filter(&self, f: impl FnMut(&[u8]) -> bool + 'static) {
+        let cb: Box<Box<dyn FnMut(&[u8]) -> bool>> = Box::new(Box::new(f));
+        unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_setConnectionFilter(
+                self.as_ptr(),
+                Box::into_raw(cb).cast(),
+                Some(connection_filter_wrapper),
+                Some(connection_filter_deleter),
+            );
+        }
+    }
+
+    /// Returns the number of RPC sessions currently connected to this server.
+    ///
+    /// Useful for leak-detection tests that open and drop many sessions and want to assert the
+    /// count returns to its baseline once the client-side handles are dropped.
+    pub fn num_sessions(&self) -> u64 {
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer.
+        unsafe { binder_rpc_unstable_bindgen::ARpcServer_getSessionCount(self.as_ptr()) }
+    }
+
+    /// Enumerates the RPC sessions currently connected to this server, along with the number of
+    /// live remote binder references each one holds.
+    ///
+    /// This is the per-session counterpart to [`num_sessions`](Self::num_sessions): services that
+    /// need to observe RPC session topology for diagnostics or shutdown coordination (e.g. "which
+    /// sessions are still holding binders, so it's safe to tear this one down") should use this
+    /// instead of trying to infer per-session state from the aggregate count alone.
+    pub fn sessions(&self) -> Vec<SessionHandle> {
+        let capacity = self.num_sessions() as usize;
+        let mut session_ids = vec![0u64; capacity];
+        let mut binder_ref_counts = vec![0u64; capacity];
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer; `session_ids` and
+        // `binder_ref_counts` are valid buffers of `capacity` elements each, which is also the
+        // capacity passed in. The native call returns the number of entries it actually filled
+        // in, which may be less than `capacity` if a session disconnected between the
+        // `num_sessions` call above and this one.
+        let filled = unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_getSessions(
+                self.as_ptr(),
+                session_ids.as_mut_ptr(),
+                binder_ref_counts.as_mut_ptr(),
+                capacity,
+            )
+        };
+        session_ids
+            .into_iter()
+            .zip(binder_ref_counts)
+            .take(filled)
+            .map(|(session_id, binder_ref_count)| SessionHandle { session_id, binder_ref_count })
+            .collect()
+    }
+}
+
+/// A handle describing one RPC session currently connected to an [`RpcServer`], as returned by
+/// [`RpcServerRef::sessions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionHandle {
+    /// This session's id, stable for its lifetime and unique among the server's concurrently
+    /// connected sessions.
+    pub session_id: u64,
+    /// The number of binder objects this session currently holds a live remote reference to.
+    pub binder_ref_count: u64,
 }
 
 extern "C" fn per_session_cb_wrapper(
@@ -230,14 +412,125 @@ extern "C" fn per_session_cb_deleter(cb: *mut c_char) {
     let _ = unsafe { Box::<Box<dyn PerSessionCallback>>::from_raw(cb.cast()) };
 }
 
+extern "C" fn connection_filter_wrapper(
+    rust_cb: *mut c_char,
+    addr: *const c_void,
+    addr_len: size_t,
+) -> bool {
+    let cb_ptr: *mut Box<dyn FnMut(&[u8]) -> bool> = rust_cb.cast();
+    // SAFETY: This callback should only get called while the RpcServer is alive.
+    let cb = unsafe { &mut *cb_ptr };
+
+    // SAFETY: The address should be a valid slice of addr_len bytes.
+    let addr = unsafe { std::slice::from_raw_parts(addr.cast(), addr_len) };
+
+    cb(addr)
+}
+
+extern "C" fn connection_filter_deleter(cb: *mut c_char) {
+    // SAFETY: shared_ptr calls this to delete the pointer we gave it.
+    // It should only get called once the last shared reference goes away.
+    let _ = unsafe { Box::<Box<dyn FnMut(&[u8]) -> bool>>::from_raw(cb.cast()) };
+}
+
+extern "C" fn ready_cb_wrapper(rust_cb: *mut c_char) {
+    let cb_ptr: *mut Option<Box<dyn FnOnce() + Send>> = rust_cb.cast();
+    // SAFETY: `rust_cb` is the cookie we passed to `ARpcServer_startWithReadyCallback`, which is
+    // kept alive until `ready_cb_deleter` runs. This only takes the callback out, leaving the
+    // cookie itself for the deleter to free.
+    if let Some(cb) = unsafe { (*cb_ptr).take() } {
+        cb();
+    }
+}
+
+extern "C" fn ready_cb_deleter(cb: *mut c_char) {
+    // SAFETY: The server calls this exactly once to free the cookie, after any call to
+    // `ready_cb_wrapper` has returned.
+    let _ = unsafe { Box::<Option<Box<dyn FnOnce() + Send>>>::from_raw(cb.cast()) };
+}
+
 #[cfg(not(target_os = "trusty"))]
 impl RpcServerRef {
+    /// Sets the maximum number of threads the server will use to handle incoming transactions.
+    ///
+    /// Must be called before [`start`](Self::start)/[`join`](Self::join); the server pool size is
+    /// fixed once it begins accepting connections.
+    pub fn set_max_threads(&self, num_threads: u32) {
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer.
+        unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_setMaxThreads(self.as_ptr(), num_threads)
+        };
+    }
+
     /// Starts a new background thread and calls join(). Returns immediately.
     pub fn start(&self) {
         // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer.
         unsafe { binder_rpc_unstable_bindgen::ARpcServer_start(self.as_ptr()) };
     }
 
+    /// Like [`start`](Self::start), but calls `ready_callback` exactly once, after the listening
+    /// socket is bound but before the server blocks on accepting connections.
+    ///
+    /// This lets callers that bound to an OS-assigned port (port `0` to `new_vsock`/`new_inet`)
+    /// synchronize clients against the real port via [`get_port`](Self::get_port) without racing
+    /// the background thread.
+    pub fn start_with_ready_callback(&self, ready_callback: impl FnOnce() + Send + 'static) {
+        let cb: Box<Option<Box<dyn FnOnce() + Send>>> = Box::new(Some(Box::new(ready_callback)));
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer. The server calls
+        // `ready_cb_wrapper` at most once, from the background thread it starts, and separately
+        // calls `ready_cb_deleter` exactly once to free the cookie once it is no longer needed,
+        // whether or not the ready callback was ever invoked (e.g. on bind failure).
+        unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_startWithReadyCallback(
+                self.as_ptr(),
+                Box::into_raw(cb).cast(),
+                Some(ready_cb_wrapper),
+                Some(ready_cb_deleter),
+            );
+        }
+    }
+
+    /// Returns the port this server is bound to, or `None` if it is not bound to an IP/vsock
+    /// port (e.g. a Unix domain socket or bootstrap-FD server).
+    ///
+    /// Useful after constructing a server with port `0` to learn the OS-assigned port; callers
+    /// should wait for [`start_with_ready_callback`](Self::start_with_ready_callback)'s callback
+    /// before calling this to avoid racing the bind.
+    pub fn get_port(&self) -> Option<u32> {
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer.
+        let port = unsafe { binder_rpc_unstable_bindgen::ARpcServer_getPort(self.as_ptr()) };
+        if port == 0 {
+            None
+        } else {
+            Some(port)
+        }
+    }
+
+    /// Pins the binder RPC wire-protocol version this server negotiates with clients, instead of
+    /// always offering the linked libbinder's default (latest) version.
+    ///
+    /// Useful for rolling upgrades where a newer server must stay compatible with older clients,
+    /// or for tests that must exercise a specific protocol generation. Fails with `InvalidInput`
+    /// if `version` exceeds the native `RPC_WIRE_PROTOCOL_VERSION` ceiling.
+    pub fn set_protocol_version(&self, version: u32) -> Result<(), Error> {
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer.
+        if unsafe {
+            binder_rpc_unstable_bindgen::ARpcServer_setProtocolVersion(self.as_ptr(), version)
+        } {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::InvalidInput))
+        }
+    }
+
+    /// Returns the wire-protocol version this server currently negotiates with clients, i.e. the
+    /// value most recently set via [`set_protocol_version`](Self::set_protocol_version), or the
+    /// linked libbinder's default if it was never called.
+    pub fn protocol_version(&self) -> u32 {
+        // SAFETY: RpcServerRef wraps a valid pointer to an ARpcServer.
+        unsafe { binder_rpc_unstable_bindgen::ARpcServer_getProtocolVersion(self.as_ptr()) }
+    }
+
     /// Joins the RpcServer thread. The call blocks until the server terminates.
     /// This must be called from exactly one thread.
     pub fn join(&self) {
@@ -317,3 +610,82 @@ impl tipc::UnbufferedService for RpcServer {
         unsafe { binder_rpc_unstable_bindgen::ARpcServer_handleTipcDisconnect(conn.ctx) };
     }
 }
+
+/// A long-term Ed25519 identity used to authenticate a mutually-authenticated Trusty RPC
+/// session: a 32-byte private seed and the corresponding 32-byte public key. Both are opaque
+/// byte strings handed to the native signing/verification implementation, which performs the
+/// actual handshake and per-frame AES-GCM sealing (mirroring how TLS certificate handling for
+/// the non-Trusty transport above is done natively rather than in this crate).
+#[cfg(target_os = "trusty")]
+#[derive(Clone)]
+pub struct TrustyIdentity {
+    pub private_key: [u8; 32],
+    pub public_key: [u8; 32],
+}
+
+#[cfg(target_os = "trusty")]
+impl RpcSessionRef {
+    /// Upgrades this session to a mutually-authenticated, encrypted channel before connecting.
+    ///
+    /// Each side generates an ephemeral X25519 keypair and exchanges ephemeral public keys
+    /// signed by `identity`'s long-term Ed25519 key; the handshake is aborted if the peer's
+    /// signature fails to verify or its public key doesn't match `expected_peer_public_key`. The
+    /// resulting X25519 shared secret is expanded with HKDF-SHA256 into per-direction AES-GCM
+    /// keys, and every RPC frame after the handshake is sealed under a per-direction
+    /// monotonically increasing nonce counter; the session is torn down outright on nonce reuse
+    /// or a failed authentication tag rather than accepting a partially-decrypted frame.
+    ///
+    /// Must be called before connecting (e.g. before `setup_trusty_client`).
+    pub fn set_mutual_authentication(
+        &self,
+        identity: &TrustyIdentity,
+        expected_peer_public_key: &[u8; 32],
+    ) -> Result<(), Error> {
+        // SAFETY: `identity` and `expected_peer_public_key` are valid for the duration of this
+        // call; the native implementation copies the key material it needs rather than
+        // retaining these pointers.
+        let ok = unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setMutualAuthentication(
+                self.as_ptr(),
+                identity.private_key.as_ptr(),
+                identity.public_key.as_ptr(),
+                expected_peer_public_key.as_ptr(),
+            )
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::PermissionDenied))
+        }
+    }
+}
+
+#[cfg(not(target_os = "trusty"))]
+impl RpcSessionRef {
+    /// Pins a trusted server certificate that this session's TLS transport will accept. Peers
+    /// presenting a certificate outside this set are rejected before any binder traffic flows.
+    ///
+    /// Must be called before connecting; the full set of trusted certificates is fixed up front
+    /// rather than grown incrementally.
+    pub fn add_trusted_peer_certificate(
+        &self,
+        format: CertificateFormat,
+        cert: &[u8],
+    ) -> Result<(), Error> {
+        // SAFETY: `cert` is a valid slice for `cert.len()` bytes for the duration of this call,
+        // which copies the data it needs rather than retaining the pointer.
+        let ok = unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_addTrustedPeerCertificate(
+                self.as_ptr(),
+                format.into(),
+                cert.as_ptr().cast(),
+                cert.len(),
+            )
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::InvalidInput))
+        }
+    }
+}
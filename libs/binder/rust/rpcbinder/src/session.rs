@@ -0,0 +1,192 @@
+/*
+ * Copyright (C) 2022 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use binder::{unstable_api::new_spibinder, SpIBinder};
+use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
+use std::io::{Error, ErrorKind};
+
+#[cfg(not(target_os = "trusty"))]
+use std::ffi::CString;
+#[cfg(not(target_os = "trusty"))]
+use std::os::unix::io::{IntoRawFd, OwnedFd};
+
+/// The ways a `ParcelFileDescriptor` argument can be transported across an RPC binder session, as
+/// negotiated between an [`RpcServer`](crate::RpcServer)'s
+/// [`set_supported_file_descriptor_transport_modes`](crate::RpcServerRef::set_supported_file_descriptor_transport_modes)
+/// and a session's [`set_file_descriptor_transport_mode`](RpcSessionRef::set_file_descriptor_transport_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDescriptorTransportMode {
+    /// File descriptors are not supported; sending one fails the transaction.
+    None,
+    /// File descriptors are passed using `SCM_RIGHTS` over the underlying Unix domain socket.
+    Unix,
+    /// File descriptors are passed using the Trusty kernel's memory-reference handle mechanism.
+    Trusty,
+}
+
+impl From<FileDescriptorTransportMode>
+    for binder_rpc_unstable_bindgen::ARpcSession_FileDescriptorTransportMode
+{
+    fn from(mode: FileDescriptorTransportMode) -> Self {
+        match mode {
+            FileDescriptorTransportMode::None => {
+                binder_rpc_unstable_bindgen::ARpcSession_FileDescriptorTransportMode_NONE
+            }
+            FileDescriptorTransportMode::Unix => {
+                binder_rpc_unstable_bindgen::ARpcSession_FileDescriptorTransportMode_UNIX
+            }
+            FileDescriptorTransportMode::Trusty => {
+                binder_rpc_unstable_bindgen::ARpcSession_FileDescriptorTransportMode_TRUSTY
+            }
+        }
+    }
+}
+
+foreign_type! {
+    type CType = binder_rpc_unstable_bindgen::ARpcSession;
+    fn drop = binder_rpc_unstable_bindgen::ARpcSession_free;
+
+    /// A type that represents a foreign instance of RpcSession.
+    #[derive(Debug)]
+    pub struct RpcSession;
+    /// A borrowed RpcSession.
+    pub struct RpcSessionRef;
+}
+
+/// SAFETY: The opaque handle can be cloned freely.
+unsafe impl Send for RpcSession {}
+/// SAFETY: The underlying C++ RpcSession class is thread-safe.
+unsafe impl Sync for RpcSession {}
+
+impl RpcSession {
+    /// Creates an unconnected RpcSession. Call one of the `connect_*` methods below to establish
+    /// a session with a remote [`RpcServer`](crate::RpcServer) and obtain its root object.
+    pub fn new() -> RpcSession {
+        // SAFETY: Takes ownership of the returned handle, which has correct refcount.
+        unsafe { RpcSession::from_ptr(binder_rpc_unstable_bindgen::ARpcSession_new()) }
+    }
+}
+
+impl Default for RpcSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_os = "trusty"))]
+impl RpcSessionRef {
+    /// Connects to a binder RPC server listening on the given vsock CID/port, as started by
+    /// [`RpcServer::new_vsock`](crate::RpcServer::new_vsock), returning its root object.
+    pub fn connect_vsock(&self, cid: u32, port: u32) -> Result<SpIBinder, Error> {
+        // SAFETY: `self` wraps a valid ARpcSession; the returned pointer, if non-null, is a new
+        // strong reference that `new_spibinder` takes ownership of.
+        let binder =
+            unsafe { binder_rpc_unstable_bindgen::ARpcSession_setupVsockClient(self.as_ptr(), cid, port) };
+        self.to_spibinder(binder)
+    }
+
+    /// Connects to a binder RPC server listening on the named init-managed Unix domain socket, as
+    /// started by [`RpcServer::new_unix_domain`](crate::RpcServer::new_unix_domain), returning its
+    /// root object.
+    pub fn connect_unix_domain(&self, name: &str) -> Result<SpIBinder, Error> {
+        let name = CString::new(name).map_err(|_| Error::from(ErrorKind::InvalidInput))?;
+        // SAFETY: `self` wraps a valid ARpcSession; `name` is a valid, NUL-terminated C string for
+        // the duration of this call.
+        let binder = unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setupUnixDomainClient(
+                self.as_ptr(),
+                name.as_ptr(),
+            )
+        };
+        self.to_spibinder(binder)
+    }
+
+    /// Connects using an existing Unix domain socket pair, as started by
+    /// [`RpcServer::new_unix_domain_bootstrap`](crate::RpcServer::new_unix_domain_bootstrap),
+    /// returning the root object of the server holding the other end of the pair.
+    pub fn connect_unix_domain_bootstrap(&self, bootstrap_fd: OwnedFd) -> Result<SpIBinder, Error> {
+        // SAFETY: `self` wraps a valid ARpcSession. The session takes ownership of the bootstrap
+        // FD.
+        let binder = unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setupUnixDomainBootstrapClient(
+                self.as_ptr(),
+                bootstrap_fd.into_raw_fd(),
+            )
+        };
+        self.to_spibinder(binder)
+    }
+
+    /// Connects to a binder RPC server listening on the given IP address/port, as started by
+    /// [`RpcServer::new_inet`](crate::RpcServer::new_inet), returning its root object.
+    pub fn connect_inet(&self, address: &str, port: u32) -> Result<SpIBinder, Error> {
+        let address = CString::new(address).map_err(|_| Error::from(ErrorKind::InvalidInput))?;
+        // SAFETY: `self` wraps a valid ARpcSession; `address` is a valid, NUL-terminated C string
+        // for the duration of this call.
+        let binder = unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setupInetClient(
+                self.as_ptr(),
+                address.as_ptr(),
+                port,
+            )
+        };
+        self.to_spibinder(binder)
+    }
+
+    /// Sets the maximum number of incoming threads this session will use to handle nested/callback
+    /// transactions from the server. Must be called before connecting.
+    pub fn set_max_incoming_threads(&self, num_threads: u32) {
+        // SAFETY: `self` wraps a valid ARpcSession.
+        unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setMaxIncomingThreads(self.as_ptr(), num_threads)
+        };
+    }
+
+    /// Sets the maximum number of outgoing connections this session will open to the server. Must
+    /// be called before connecting.
+    pub fn set_max_outgoing_connections(&self, num_connections: u32) {
+        // SAFETY: `self` wraps a valid ARpcSession.
+        unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setMaxOutgoingConnections(
+                self.as_ptr(),
+                num_connections,
+            )
+        };
+    }
+
+    /// Converts a raw `AIBinder*` returned by one of the `ARpcSession_setup*Client` NDK calls
+    /// above into an owned [`SpIBinder`], treating null as a connection failure.
+    fn to_spibinder(&self, binder: *mut binder::unstable_api::AIBinder) -> Result<SpIBinder, Error> {
+        // SAFETY: `binder` was just returned by the NDK as either a new strong reference or null.
+        unsafe { new_spibinder(binder) }
+            .ok_or_else(|| Error::new(ErrorKind::Other, "Failed to connect RPC session"))
+    }
+}
+
+impl RpcSessionRef {
+    /// Sets the file descriptor transport mode this session will request when connecting. Must
+    /// match a mode the peer's `RpcServer` advertised via
+    /// [`set_supported_file_descriptor_transport_modes`](crate::RpcServerRef::set_supported_file_descriptor_transport_modes),
+    /// or `ParcelFileDescriptor` arguments will fail to cross the session.
+    pub fn set_file_descriptor_transport_mode(&self, mode: FileDescriptorTransportMode) {
+        // SAFETY: `self` wraps a valid ARpcSession.
+        unsafe {
+            binder_rpc_unstable_bindgen::ARpcSession_setFileDescriptorTransportMode(
+                self.as_ptr(),
+                mode.into(),
+            )
+        };
+    }
+}
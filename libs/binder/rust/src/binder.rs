@@ -16,11 +16,12 @@
 
 //! Trait definitions for binder objects
 
-use crate::error::Result;
+use crate::error::{Result, StatusCode};
 use crate::parcel::Parcel;
-use crate::proxy::{DeathRecipient, SpIBinder};
+use crate::proxy::{DeathRecipient, SpIBinder, WpIBinder};
 use crate::sys;
 
+use std::any::Any;
 use std::ffi::{c_void, CString};
 use std::os::unix::io::AsRawFd;
 use std::ptr;
@@ -95,6 +96,25 @@ pub trait IBinder {
     /// available.
     fn get_extension(&mut self) -> Result<Option<SpIBinder>>;
 
+    /// Set the extension for this local binder, later retrievable by clients via
+    /// [`get_extension`](Self::get_extension) on their remote reference to it.
+    ///
+    /// Only meaningful on a locally-created binder (e.g. the `Binder<T>` wrapper generated by
+    /// [`declare_binder_interface!`]); calling this on a remote proxy has no effect on the
+    /// remote object and returns an error. Lets a service expose an auxiliary interface, e.g.
+    /// for debugging, versioned add-on APIs, or vendor extensions.
+    fn set_extension(&mut self, extension: &mut SpIBinder) -> Result<()>
+    where
+        Self: AsNative<sys::AIBinder>,
+    {
+        // Safety: `self.as_native_mut()` and `extension.as_native_mut()` are both valid
+        // `AIBinder` pointers for the duration of this call, which is all
+        // `AIBinder_setExtension` requires; it takes its own strong reference to the extension.
+        let status =
+            unsafe { sys::AIBinder_setExtension(self.as_native_mut(), extension.as_native_mut()) };
+        status_result(status)
+    }
+
     /// Perform a generic operation with the object.
     ///
     /// # Arguments
@@ -134,12 +154,94 @@ pub trait IBinder {
     /// dies.
     fn unlink_to_death(&mut self, recipient: &mut DeathRecipient) -> Result<()>;
 
-    // C++ IBinder interfaces left to be implemented:
-    //
-    // Unimplemented:
-    // - attachObject
-    // - findObject
-    // - detachObject
+    /// Attach an arbitrary Rust object to this binder, keyed by `key`.
+    ///
+    /// `key` is typically the address of a function or static, used purely as a unique
+    /// identifier and never dereferenced. The same `key` must always be paired with the same
+    /// concrete `T`; attaching a different `T` under a `key` already in use is a logic error.
+    /// Overwrites (dropping) any value previously attached under the same `key`.
+    fn attach_object<T: Any + Send + Sync>(&mut self, key: *const c_void, object: Arc<T>) -> Result<()>
+    where
+        Self: AsNative<sys::AIBinder>,
+    {
+        let object: Arc<dyn Any + Send + Sync> = object;
+        let cookie = Box::into_raw(Box::new(object)) as *mut c_void;
+        // Safety: `self.as_native_mut()` is a valid `AIBinder` for the duration of this call;
+        // `cookie` points at a freshly boxed `Arc<dyn Any + Send + Sync>` that
+        // `drop_attached_object` knows how to free, and `key` is never dereferenced by either
+        // side, only compared.
+        let status = unsafe {
+            sys::AIBinder_attachObject(self.as_native_mut(), key, cookie, Some(drop_attached_object))
+        };
+        if status != 0 {
+            // Safety: the attach call failed, so the binder never took ownership of `cookie`.
+            drop(unsafe { Box::from_raw(cookie as *mut Arc<dyn Any + Send + Sync>) });
+        }
+        status_result(status)
+    }
+
+    /// Retrieve a previously [attached](Self::attach_object) object by its key and concrete
+    /// type.
+    ///
+    /// Returns [`None`] if nothing is attached under `key`, or if the attached value isn't a
+    /// `T`.
+    fn find_object<T: Any + Send + Sync>(&self, key: *const c_void) -> Option<Arc<T>>
+    where
+        Self: AsNative<sys::AIBinder>,
+    {
+        // Safety: `self.as_native()` is a valid `AIBinder` for the duration of this call; the
+        // returned cookie, if non-null, is whatever was last passed to `attach_object` for
+        // `key` and remains owned by the binder.
+        let cookie = unsafe { sys::AIBinder_findObject(self.as_native(), key) };
+        if cookie.is_null() {
+            return None;
+        }
+        // Safety: `cookie` was produced by `attach_object`'s `Box::into_raw` and is still owned
+        // by the binder, so this only borrows it rather than reclaiming it.
+        let object = unsafe { &*(cookie as *const Arc<dyn Any + Send + Sync>) };
+        object.clone().downcast::<T>().ok()
+    }
+
+    /// Remove and return a previously [attached](Self::attach_object) object.
+    ///
+    /// Returns [`None`] under the same conditions as [`find_object`](Self::find_object).
+    fn detach_object<T: Any + Send + Sync>(&mut self, key: *const c_void) -> Option<Arc<T>>
+    where
+        Self: AsNative<sys::AIBinder>,
+    {
+        // Safety: `self.as_native_mut()` is a valid `AIBinder`; the returned cookie, if
+        // non-null, is relinquished to us by the binder without running `drop_attached_object`
+        // on it.
+        let cookie = unsafe { sys::AIBinder_detachObject(self.as_native_mut(), key) };
+        if cookie.is_null() {
+            return None;
+        }
+        // Safety: see `attach_object`; this is the one place that reclaims ownership via
+        // `Box::from_raw`.
+        let object = *unsafe { Box::from_raw(cookie as *mut Arc<dyn Any + Send + Sync>) };
+        object.downcast::<T>().ok()
+    }
+}
+
+/// Frees the boxed `Arc<dyn Any + Send + Sync>` cookie created by [`IBinder::attach_object`],
+/// invoked by the binder once nothing has it attached any longer (overwritten by a later
+/// `attach_object` call under the same key, or the binder itself is destroyed).
+///
+/// # Safety
+///
+/// Called from C++. `cookie` must be a pointer previously returned by `Box::into_raw` on a
+/// `Box<Arc<dyn Any + Send + Sync>>`, not yet freed.
+unsafe extern "C" fn drop_attached_object(cookie: *mut c_void) {
+    // Safety: see function docs.
+    drop(unsafe { Box::from_raw(cookie as *mut Arc<dyn Any + Send + Sync>) });
+}
+
+fn status_result(status: sys::status_t) -> Result<()> {
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(StatusCode::UNKNOWN_ERROR.into())
+    }
 }
 
 /// Opaque reference to the type of a Binder interface.
@@ -316,7 +418,104 @@ pub trait InterfaceClassMethods {
 /// }
 /// ```
 pub trait FromIBinder {
-    fn try_from(ibinder: SpIBinder) -> Result<Arc<Self>>;
+    fn try_from(ibinder: SpIBinder) -> Result<Strong<Self>>;
+}
+
+/// A strong, typed reference to a remote or local binder interface.
+///
+/// Wraps the underlying [`SpIBinder`] together with the already-downcast `T`, so that
+/// [`Deref`](std::ops::Deref) gives direct access to the interface's methods without
+/// re-querying the interface class on every call. Obtained from [`FromIBinder::try_from`]
+/// (e.g. via [`get_interface`](crate::get_interface)), or by [upgrading](Weak::upgrade) a
+/// [`Weak<T>`].
+pub struct Strong<T: FromIBinder + ?Sized> {
+    binder: SpIBinder,
+    interface: Arc<T>,
+}
+
+impl<T: FromIBinder + ?Sized> Strong<T> {
+    /// Creates a new `Strong<T>` from an already-downcast interface and the binder it was
+    /// downcast from.
+    ///
+    /// This is a low-level constructor, normally only called by [`FromIBinder::try_from`]
+    /// implementations (including the one generated by [`declare_binder_interface!`]), which
+    /// have already verified that `interface` was obtained from `binder`.
+    pub fn new(binder: SpIBinder, interface: Arc<T>) -> Self {
+        Self { binder, interface }
+    }
+
+    /// Downgrades this strong reference to a [`Weak<T>`] that does not keep the remote service
+    /// alive.
+    pub fn downgrade(&self) -> Weak<T> {
+        Weak { binder: self.binder.downgrade(), _marker: std::marker::PhantomData }
+    }
+}
+
+impl<T: FromIBinder + ?Sized> Clone for Strong<T> {
+    fn clone(&self) -> Self {
+        Self { binder: self.binder.clone(), interface: self.interface.clone() }
+    }
+}
+
+impl<T: FromIBinder + ?Sized> std::ops::Deref for Strong<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.interface
+    }
+}
+
+/// A weak, typed reference to a remote or local binder interface, obtained from
+/// [`Strong::downgrade`].
+///
+/// Unlike [`Strong<T>`], holding a `Weak<T>` does not keep the remote service alive, which makes
+/// it suitable for caches or other bookkeeping that shouldn't leak peers. Call
+/// [`upgrade`](Weak::upgrade) to attempt to recover a `Strong<T>` before use.
+pub struct Weak<T: FromIBinder + ?Sized> {
+    binder: WpIBinder,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: FromIBinder + ?Sized> Weak<T> {
+    /// Promotes this weak reference back into a [`Strong<T>`].
+    ///
+    /// This re-associates the interface class and re-downcasts to `T`, so it fails the same way
+    /// [`FromIBinder::try_from`] would, and also fails if the remote service has since died.
+    pub fn upgrade(&self) -> Result<Strong<T>> {
+        let binder = self.binder.promote().ok_or(StatusCode::DEAD_OBJECT)?;
+        T::try_from(binder)
+    }
+}
+
+impl<T: FromIBinder + ?Sized> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        Self { binder: self.binder.clone(), _marker: std::marker::PhantomData }
+    }
+}
+
+/// Marker trait implemented by a binder interface trait object (e.g. `dyn IFoo`) to name its
+/// async-capable counterpart.
+///
+/// Implemented by [`declare_binder_interface!`] when its `async` clause is used, so generic code
+/// holding a [`Strong<dyn IFoo>`] can convert to the matching async proxy for a particular
+/// [`BinderAsyncPool`](crate::BinderAsyncPool) via [`ToAsyncInterface::Target`].
+pub trait ToAsyncInterface<P>
+where
+    Self: Sized,
+{
+    /// The async-capable proxy type generated for this interface, parameterized by `P`.
+    type Target: ?Sized;
+}
+
+/// Marker trait implemented by a generated async proxy to name back its synchronous interface.
+///
+/// The inverse of [`ToAsyncInterface`].
+pub trait ToSyncInterface
+where
+    Self: Sized,
+{
+    /// The synchronous interface trait object type this async proxy was generated from.
+    type Target: ?Sized;
 }
 
 /// Trait for transparent Rust wrappers around android C++ native types.
@@ -400,6 +599,21 @@ unsafe impl<T, V: AsNative<T>> AsNative<T> for Option<V> {
 ///     Ok(())
 /// }
 /// ```
+///
+/// An optional `async` clause additionally generates an async-capable proxy, parameterized by a
+/// [`BinderAsyncPool`](crate::BinderAsyncPool), implementing a separately-declared async
+/// interface trait (`$asyncinterface`, normally generated by the AIDL backend alongside
+/// `$interface`):
+///
+/// ```rust,ignore
+/// declare_binder_interface! {
+///     IServiceManager["android.os.IServiceManager"] {
+///         native: BnServiceManager(on_transact),
+///         proxy: BpServiceManager,
+///         async: IServiceManagerAsync(BpServiceManagerAsync, P),
+///     }
+/// }
+/// ```
 #[macro_export]
 macro_rules! declare_binder_interface {
     {
@@ -517,18 +731,84 @@ macro_rules! declare_binder_interface {
         }
 
         impl $crate::FromIBinder for dyn $interface {
-            fn try_from(mut ibinder: $crate::SpIBinder) -> $crate::Result<std::sync::Arc<dyn $interface>> {
+            fn try_from(mut ibinder: $crate::SpIBinder) -> $crate::Result<$crate::Strong<dyn $interface>> {
                 if !ibinder.associate_class(<$native as $crate::Remotable>::get_class()) {
                     return Err(StatusCode::BAD_TYPE.into());
                 }
 
                 let service: $crate::Result<$crate::Binder<$native>> = std::convert::TryFrom::try_from(ibinder.clone());
-                if let Ok(service) = service {
-                    Ok(service.as_interface())
+                let interface: std::sync::Arc<dyn $interface> = if let Ok(service) = service {
+                    service.as_interface()
                 } else {
-                    Ok(std::sync::Arc::new(<$proxy as $crate::Proxy>::from_binder(ibinder)?))
-                }
+                    std::sync::Arc::new(<$proxy as $crate::Proxy>::from_binder(ibinder.clone())?)
+                };
+                Ok($crate::Strong::new(ibinder, interface))
+            }
+        }
+    };
+
+    {
+        $interface:path[$descriptor:expr] {
+            native: $native:ident($ontransact:path),
+            proxy: $proxy:ident,
+            async: $asyncinterface:ident($asyncproxy:ident, $pool:ident),
+        }
+    } => {
+        $crate::declare_binder_interface! {
+            $interface[$descriptor] {
+                native: $native($ontransact),
+                proxy: $proxy,
+            }
+        }
+
+        $crate::declare_binder_interface_async! {
+            $interface, $proxy, $asyncinterface($asyncproxy, $pool)
+        }
+    };
+}
+
+/// Generates the async proxy glue for the `async` clause of [`declare_binder_interface!`].
+///
+/// Not meant to be invoked directly; [`declare_binder_interface!`] delegates to this when its
+/// `async` clause is present. `$asyncinterface` names the async-capable interface trait
+/// (mirroring `$interface` but with methods returning
+/// [`BoxFuture`](crate::BoxFuture)`<Result<T>>`), which must be declared separately (normally by
+/// the AIDL backend) and implemented for the generated `$asyncproxy<$pool>`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! declare_binder_interface_async {
+    ($interface:path, $proxy:ident, $asyncinterface:ident($asyncproxy:ident, $pool:ident)) => {
+        #[doc = concat!(
+            "Async-capable proxy for [`", stringify!($interface), "`], implementing the ",
+            "generated [`", stringify!($asyncinterface), "`] trait.\n\n",
+            "Each method builds its request `Parcel` eagerly, then hands the blocking ",
+            "`transact` call to the `", stringify!($pool), ": ", stringify!($crate::BinderAsyncPool),
+            "` the caller chose, so the caller's task is never blocked on the underlying FFI call.",
+        )]
+        pub struct $asyncproxy<$pool> {
+            binder: $proxy,
+            pool: std::marker::PhantomData<$pool>,
+        }
+
+        impl<$pool: $crate::BinderAsyncPool> $asyncproxy<$pool> {
+            /// Wraps a synchronous proxy so its methods can be awaited from async code.
+            pub fn from_binder(binder: $proxy) -> Self {
+                Self { binder, pool: std::marker::PhantomData }
             }
+
+            /// Returns the underlying synchronous proxy, e.g. to call a method the async
+            /// interface doesn't cover.
+            pub fn as_sync(&self) -> &$proxy {
+                &self.binder
+            }
+        }
+
+        impl<$pool: $crate::BinderAsyncPool> $crate::binder_impl::ToAsyncInterface<$pool> for dyn $interface {
+            type Target = $asyncproxy<$pool>;
+        }
+
+        impl<$pool> $crate::binder_impl::ToSyncInterface for $asyncproxy<$pool> {
+            type Target = dyn $interface;
         }
     };
 }
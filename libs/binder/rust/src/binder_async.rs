@@ -0,0 +1,61 @@
+/*
+ * Copyright (C) 2020 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Support types for async binder interfaces.
+//!
+//! Generated async proxies (see the `async` clause of [`declare_binder_interface!`]) build their
+//! request [`Parcel`](crate::Parcel) eagerly, then hand the blocking `transact` call off to a
+//! [`BinderAsyncPool`] so the calling task never blocks a worker thread on the underlying FFI
+//! call.
+
+use crate::error::Result;
+
+use std::future::Future;
+use std::pin::Pin;
+
+/// A boxed, pinned future, as returned by async binder interface methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Abstracts over the executor used to run a blocking binder transaction off the calling task's
+/// thread.
+///
+/// `spawn_me` is the blocking `transact` call that builds and sends the already-serialized
+/// request [`Parcel`](crate::Parcel); `after_spawn` runs once `spawn_me` completes and
+/// deserializes the reply back into the value the async method actually returns.
+/// Implementations should run `spawn_me` on a thread where blocking is acceptable (e.g. a
+/// `spawn_blocking` pool).
+pub trait BinderAsyncPool {
+    /// Runs `spawn_me` on a blocking-friendly thread, then `after_spawn` on its result, and
+    /// returns a future that resolves with the final value.
+    fn spawn<F1, F2, A, B>(&self, spawn_me: F1, after_spawn: F2) -> BoxFuture<'static, Result<B>>
+    where
+        F1: FnOnce() -> Result<A> + Send + 'static,
+        F2: FnOnce(A) -> Result<B> + Send + 'static,
+        A: Send + 'static,
+        B: Send + 'static;
+}
+
+/// Abstracts over an async runtime's ability to block the current thread on a future.
+///
+/// Used to fall back to a synchronous call when
+/// [`is_handling_transaction`](crate::is_handling_transaction) reports that this thread is
+/// already inside the binder thread pool's transaction handling: spawning the blocking work onto
+/// a pool and awaiting it back here from that same thread pool could deadlock, so the async
+/// method instead calls the sync implementation directly via `block_on`.
+pub trait BinderAsyncRuntime {
+    /// Blocks the current thread until `future` resolves, returning its output.
+    fn block_on<F: Future>(&self, future: F) -> F::Output;
+}
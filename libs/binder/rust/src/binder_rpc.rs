@@ -0,0 +1,165 @@
+/*
+ * Copyright (C) 2023 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Expose local [`Remotable`](crate::binder_impl::Remotable) services over a socket-based RPC
+//! transport (VSOCK, Unix domain socket), instead of requiring peers to share a kernel binder
+//! context.
+//!
+//! This is built on the same native `ARpcServer`/`ARpcSession` machinery the standalone
+//! `rpcbinder` crate uses, but hands back this crate's own [`SpIBinder`], so the root object
+//! dispatches through the ordinary `InterfaceClass`/`on_transact` machinery and the generated
+//! [`FromIBinder::try_from`](crate::FromIBinder::try_from) works unchanged on either transport.
+
+use crate::binder::AsNative;
+use crate::error::{Result, StatusCode};
+use crate::proxy::SpIBinder;
+use crate::sys;
+use crate::unstable_api::new_spibinder;
+
+use std::os::unix::io::RawFd;
+
+/// Serves a root [`SpIBinder`] to peers connecting over VSOCK or a Unix domain socket.
+///
+/// Dropping an `RpcServer` that was ever [`start`](Self::start)ed implicitly
+/// [`shutdown`](Self::shutdown)s it.
+pub struct RpcServer {
+    ptr: *mut sys::ARpcServer,
+}
+
+// Safety: `ARpcServer` is reference-counted and safe to use from any thread; the native API
+// guards its own internal locking.
+unsafe impl Send for RpcServer {}
+unsafe impl Sync for RpcServer {}
+
+impl RpcServer {
+    /// Creates a server that will serve `root` to clients connecting to `cid`/`port` over VSOCK.
+    pub fn new_vsock(root: SpIBinder, cid: u32, port: u32) -> Result<Self> {
+        // Safety: `root`'s underlying `AIBinder` is a valid pointer for the lifetime of this
+        // call, which is all `ARpcServer_newVsock` requires; it takes its own strong reference.
+        let ptr = unsafe { sys::ARpcServer_newVsock(root.as_native_mut(), cid, port) };
+        if ptr.is_null() {
+            return Err(StatusCode::UNKNOWN_ERROR.into());
+        }
+        Ok(Self { ptr })
+    }
+
+    /// Creates a server that will serve `root` to clients connecting to the Unix domain socket
+    /// already bound to `fd`.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be a valid, bound, listening socket file descriptor that this `RpcServer` may
+    /// take ownership of.
+    pub unsafe fn new_unix_domain(root: SpIBinder, fd: RawFd) -> Result<Self> {
+        let ptr = unsafe { sys::ARpcServer_newUnixDomain(root.as_native_mut(), fd) };
+        if ptr.is_null() {
+            return Err(StatusCode::UNKNOWN_ERROR.into());
+        }
+        Ok(Self { ptr })
+    }
+
+    /// Sets the maximum number of threads the server's thread pool may use to process incoming
+    /// transactions.
+    ///
+    /// Must be called before [`start`](Self::start).
+    pub fn set_max_threads(&self, threads: usize) {
+        // Safety: `self.ptr` is a valid `ARpcServer` for the lifetime of `self`.
+        unsafe { sys::ARpcServer_setMaxThreads(self.ptr, threads as u32) };
+    }
+
+    /// Starts the server's thread pool in the background and returns immediately.
+    pub fn start(&self) {
+        // Safety: `self.ptr` is a valid `ARpcServer` for the lifetime of `self`.
+        unsafe { sys::ARpcServer_start(self.ptr) };
+    }
+
+    /// Joins the server's thread pool, blocking the calling thread until the server shuts down.
+    ///
+    /// Can be called instead of [`start`](Self::start) to run the server on the calling thread.
+    pub fn join(&self) {
+        // Safety: `self.ptr` is a valid `ARpcServer` for the lifetime of `self`.
+        unsafe { sys::ARpcServer_join(self.ptr) };
+    }
+
+    /// Shuts the server down, causing any thread blocked in [`join`](Self::join) to return.
+    pub fn shutdown(&self) -> Result<()> {
+        // Safety: `self.ptr` is a valid `ARpcServer` for the lifetime of `self`.
+        if unsafe { sys::ARpcServer_shutdown(self.ptr) } {
+            Ok(())
+        } else {
+            Err(StatusCode::UNKNOWN_ERROR.into())
+        }
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        // Safety: `self.ptr` is a valid, uniquely-owned `ARpcServer`, and this is the only place
+        // that frees it.
+        unsafe { sys::ARpcServer_free(self.ptr) };
+    }
+}
+
+/// Client side of a socket-based RPC connection to an [`RpcServer`].
+pub struct RpcSession {
+    ptr: *mut sys::ARpcSession,
+}
+
+unsafe impl Send for RpcSession {}
+unsafe impl Sync for RpcSession {}
+
+impl RpcSession {
+    /// Creates a new, unconnected session.
+    pub fn new() -> Self {
+        // Safety: `ARpcSession_new` takes no arguments and always returns a valid, owned
+        // pointer.
+        Self { ptr: unsafe { sys::ARpcSession_new() } }
+    }
+
+    /// Connects to a server listening on `cid`/`port` over VSOCK, returning its root object.
+    pub fn setup_vsock_client(&self, cid: u32, port: u32) -> Result<SpIBinder> {
+        // Safety: `self.ptr` is a valid `ARpcSession` for the lifetime of `self`; the returned
+        // pointer, if non-null, is a new strong reference that `new_spibinder` takes ownership
+        // of.
+        let binder = unsafe { sys::ARpcSession_setupVsockClient(self.ptr, cid, port) };
+        // Safety: `binder` was just returned by the NDK as a new strong reference, or is null.
+        unsafe { new_spibinder(binder) }.ok_or_else(|| StatusCode::UNKNOWN_ERROR.into())
+    }
+
+    /// Connects to a server listening on the Unix domain socket at `path`, returning its root
+    /// object.
+    pub fn setup_unix_domain_client(&self, path: &str) -> Result<SpIBinder> {
+        let path = std::ffi::CString::new(path).map_err(|_| StatusCode::UNEXPECTED_NULL)?;
+        // Safety: `self.ptr` is a valid `ARpcSession`; `path` is a valid, NUL-terminated C
+        // string for the duration of the call.
+        let binder = unsafe { sys::ARpcSession_setupUnixDomainClient(self.ptr, path.as_ptr()) };
+        unsafe { new_spibinder(binder) }.ok_or_else(|| StatusCode::UNKNOWN_ERROR.into())
+    }
+}
+
+impl Default for RpcSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for RpcSession {
+    fn drop(&mut self) {
+        // Safety: `self.ptr` is a valid, uniquely-owned `ARpcSession`, and this is the only
+        // place that frees it.
+        unsafe { sys::ARpcSession_free(self.ptr) };
+    }
+}
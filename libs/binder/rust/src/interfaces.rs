@@ -0,0 +1,69 @@
+//! Manually implemented AIDL interfaces and the transports that serve them.
+//!
+//! Local binder objects in this module are authored against the simplified [`Binder`] trait
+//! below rather than the lower-level [`Remotable`](crate::binder_impl::Remotable) directly: one
+//! `on_transact` taking `Option<&mut Parcel>` handles both oneway and two-way calls, instead of
+//! the `Remotable`/`InterfaceClass`/`Arc` machinery a `declare_binder_interface!`-generated
+//! native side needs. [`Service<T>`] is the cheaply-cloneable handle callers pass around;
+//! [`rpc::root_binder`] is the adapter that turns one into an ordinary
+//! [`SpIBinder`](crate::SpIBinder) when it actually needs to cross a transaction.
+
+pub mod async_service;
+pub mod rpc;
+pub mod service_manager;
+
+use crate::binder_impl::{Parcel, TransactionCode, TransactionFlags};
+use crate::Result;
+use std::sync::Arc;
+
+/// A locally-implemented binder interface in this module's simplified world (see the module
+/// docs above).
+pub trait Binder {
+    /// This interface's Binder descriptor, matching the remote proxy's interface descriptor.
+    const INTERFACE_DESCRIPTOR: &'static str;
+
+    /// Handles transaction `code`. `reply` is `None` for a oneway call, i.e. one the caller
+    /// doesn't block waiting on a response to. Objects published through
+    /// [`rpc::root_binder`] are dispatched via `Remotable::on_transact`, which carries no
+    /// flags: they always receive `Some(reply)` and `flags` of 0, oneway calls included.
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        data: &Parcel,
+        reply: Option<&mut Parcel>,
+        flags: TransactionFlags,
+    ) -> Result<()>;
+}
+
+/// A cheaply-cloneable handle to a locally-implemented [`Binder`], suitable for publishing as an
+/// ordinary [`SpIBinder`](crate::SpIBinder) (see [`rpc::root_binder`]) or sending to a remote
+/// process as a callback object (see
+/// [`service_manager::IServiceManager::register_for_notifications`]).
+pub struct Service<T>(Arc<T>);
+
+impl<T> Service<T> {
+    /// Wraps `inner` so it can be published as a binder object.
+    pub fn new(inner: T) -> Self {
+        Self(Arc::new(inner))
+    }
+}
+
+impl<T> Clone for Service<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: Binder> Binder for Service<T> {
+    const INTERFACE_DESCRIPTOR: &'static str = T::INTERFACE_DESCRIPTOR;
+
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        data: &Parcel,
+        reply: Option<&mut Parcel>,
+        flags: TransactionFlags,
+    ) -> Result<()> {
+        self.0.on_transact(code, data, reply, flags)
+    }
+}
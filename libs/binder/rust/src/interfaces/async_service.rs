@@ -0,0 +1,60 @@
+//! Async front-end for [`IServiceManager`], built atop the generic [`BinderAsyncPool`] executor
+//! bridge so a caller on a Tokio (or any other) runtime can await a binder call instead of
+//! blocking a worker thread on it.
+
+use super::service_manager::IServiceManager;
+use crate::binder_async::{BinderAsyncPool, BoxFuture};
+use crate::{Result, SpIBinder};
+
+/// Async front-end for [`IServiceManager`], generic over any synchronous implementation --
+/// most commonly `BpServiceManager` -- so a proxy can expose both the sync trait and this async
+/// variant, letting callers choose.
+///
+/// Every method dispatches the underlying synchronous transaction on `P`'s blocking-friendly
+/// executor rather than running it on the calling task. [`wait_for_service`](Self::wait_for_service)
+/// benefits the most: its synchronous counterpart parks an entire OS thread on a condvar until
+/// the service appears, which is exactly what an async runtime's worker threads can't afford.
+pub struct AsyncServiceManager<T, P> {
+    inner: T,
+    pool: P,
+}
+
+impl<T, P> AsyncServiceManager<T, P>
+where
+    T: IServiceManager + Clone + Send + 'static,
+    P: BinderAsyncPool,
+{
+    /// Wraps `inner`, dispatching its blocking transactions on `pool`.
+    pub fn new(inner: T, pool: P) -> Self {
+        Self { inner, pool }
+    }
+
+    /// Async equivalent of [`IServiceManager::get_service`].
+    pub fn get_service(&self, name: &str) -> BoxFuture<'static, Result<SpIBinder>> {
+        self.spawn_sync(name, IServiceManager::get_service)
+    }
+
+    /// Async equivalent of [`IServiceManager::check_service`].
+    pub fn check_service(&self, name: &str) -> BoxFuture<'static, Result<SpIBinder>> {
+        self.spawn_sync(name, IServiceManager::check_service)
+    }
+
+    /// Async equivalent of [`IServiceManager::wait_for_service`]. Unlike its synchronous
+    /// counterpart, this never parks the calling task's worker thread: the blocking wait runs
+    /// entirely on `pool`'s executor.
+    pub fn wait_for_service(&self, name: &str) -> BoxFuture<'static, Result<SpIBinder>> {
+        self.spawn_sync(name, IServiceManager::wait_for_service)
+    }
+
+    /// Runs `method` against a clone of `inner` on `pool`'s blocking-friendly executor, resolving
+    /// the returned future with whatever `method` returns.
+    fn spawn_sync(
+        &self,
+        name: &str,
+        method: fn(&mut T, &str) -> Result<SpIBinder>,
+    ) -> BoxFuture<'static, Result<SpIBinder>> {
+        let mut inner = self.inner.clone();
+        let name = name.to_string();
+        self.pool.spawn(move || method(&mut inner, &name), Ok)
+    }
+}
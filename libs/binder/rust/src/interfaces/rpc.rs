@@ -0,0 +1,85 @@
+//! Serves this module's `Service<T>`/[`Binder`] world (see [`super::service_manager`]) over RPC
+//! binder, reusing the existing VSOCK/Unix domain socket transport in [`crate::binder_rpc`]
+//! instead of standing up a second, bespoke wire protocol.
+//!
+//! [`binder_rpc::RpcServer`](crate::binder_rpc::RpcServer) and
+//! [`binder_rpc::RpcSession`](crate::binder_rpc::RpcSession) already serve/connect to an
+//! arbitrary [`SpIBinder`] root object over that transport, built on the same native
+//! `ARpcServer`/`ARpcSession` machinery the standalone `rpcbinder` crate uses. [`ServiceRemotable`]
+//! is the thin adapter that lets a [`Service<T>`] ride that transport unchanged: it forwards
+//! `on_transact` to the wrapped service and turns it into an ordinary [`SpIBinder`] the same way
+//! any other local object does, via [`Remotable`]/[`binder_impl::Binder`].
+//!
+//! The client side needs no adapter at all -- a peer connecting with
+//! [`RpcSession::setup_vsock_client`](crate::binder_rpc::RpcSession::setup_vsock_client) or
+//! [`setup_unix_domain_client`](crate::binder_rpc::RpcSession::setup_unix_domain_client) already
+//! gets back a plain [`SpIBinder`], which [`FromIBinder::try_from`] turns into whatever interface
+//! the caller expects.
+
+use crate::binder_impl::{Binder as NativeBinder, InterfaceClass, Parcel, Remotable, TransactionCode};
+use crate::binder_rpc::RpcServer;
+use crate::interfaces::{Binder, Service};
+use crate::{Result, SpIBinder};
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+
+/// Adapts a [`Service<T>`] to this crate's [`Remotable`], so it can be published through
+/// [`binder_rpc::RpcServer`](crate::binder_rpc::RpcServer) like any other local binder object.
+struct ServiceRemotable<T>(Service<T>);
+
+impl<T: Binder + Send + Sync + 'static> Remotable for ServiceRemotable<T>
+where
+    Service<T>: Clone + Send + Sync + 'static,
+{
+    type Interface = Service<T>;
+
+    fn get_descriptor() -> &'static str {
+        T::INTERFACE_DESCRIPTOR
+    }
+
+    fn on_transact(&self, code: TransactionCode, data: &Parcel, reply: &mut Parcel) -> Result<()> {
+        self.0.on_transact(code, data, Some(reply), 0)
+    }
+
+    fn get_class() -> InterfaceClass {
+        InterfaceClass::new::<NativeBinder<Self>>()
+    }
+
+    fn as_interface(&self) -> Arc<Self::Interface> {
+        Arc::new(self.0.clone())
+    }
+}
+
+/// Publishes `service` as the root object of an [`RpcServer`] listening on `cid`/`port` over
+/// VSOCK.
+pub fn serve_vsock<T>(service: Service<T>, cid: u32, port: u32) -> Result<RpcServer>
+where
+    T: Binder + Send + Sync + 'static,
+    Service<T>: Clone + Send + Sync + 'static,
+{
+    RpcServer::new_vsock(root_binder(service), cid, port)
+}
+
+/// Publishes `service` as the root object of an [`RpcServer`] listening on the already-bound,
+/// listening Unix domain socket `fd`.
+///
+/// # Safety
+///
+/// `fd` must be a valid, bound, listening socket file descriptor that the server may take
+/// ownership of.
+pub unsafe fn serve_unix_domain<T>(service: Service<T>, fd: RawFd) -> Result<RpcServer>
+where
+    T: Binder + Send + Sync + 'static,
+    Service<T>: Clone + Send + Sync + 'static,
+{
+    // Safety: forwarded to the caller of this function.
+    unsafe { RpcServer::new_unix_domain(root_binder(service), fd) }
+}
+
+pub(crate) fn root_binder<T>(service: Service<T>) -> SpIBinder
+where
+    T: Binder + Send + Sync + 'static,
+    Service<T>: Clone + Send + Sync + 'static,
+{
+    NativeBinder::new(ServiceRemotable(service)).as_binder()
+}
@@ -1,17 +1,11 @@
 //! Manually implemented IServiceManager AIDL interface.
 
-use crate::binder::IBinder;
-use crate::parcel::Parcel;
+use crate::binder_impl::{Parcel, Proxy, TransactionCode, TransactionFlags};
+use crate::interfaces::{Binder, Service};
 use crate::service_manager::DumpFlags;
-use crate::sys::Status;
 use crate::utils::String16;
-use crate::{Binder, Interface, Result, Service};
-
-declare_binder_interface!(
-    BpServiceManager,
-    IServiceManager,
-    "android.os.IServiceManager"
-);
+use crate::{IBinder, Result, SpIBinder, Status};
+use std::sync::{Arc, Condvar, Mutex};
 
 /// Binder interface for finding and publishing system services.
 pub trait IServiceManager {
@@ -23,14 +17,14 @@ pub trait IServiceManager {
     /// legacy purposes.
     ///
     /// Returns null if the service does not exist.
-    fn get_service(&mut self, name: &str) -> Result<Interface>;
+    fn get_service(&mut self, name: &str) -> Result<SpIBinder>;
 
     /// Retrieve an existing service called `name` from the service
     /// manager. Non-blocking. Returns null if the service does not exist.
-    fn check_service(&mut self, name: &str) -> Result<Interface>;
+    fn check_service(&mut self, name: &str) -> Result<SpIBinder>;
 
     /// Place a new service called `name` into the service manager.
-    fn add_service<T: Binder>(
+    fn add_service<T: Binder + Send + Sync + 'static>(
         &mut self,
         name: &str,
         service: &Service<T>,
@@ -45,104 +39,365 @@ pub trait IServiceManager {
     /// is not started yet. For instance, this could be a service declared in
     /// the VINTF manifest.
     fn is_declared(&mut self, name: &str) -> Result<bool>;
+
+    /// Returns the instance names of all declared (but not necessarily running) services that
+    /// implement the interface named `name`, e.g. all instances of `android.hardware.foo.IFoo`.
+    fn get_declared_instances(&mut self, name: &str) -> Result<Vec<String16>>;
+
+    /// Returns the APEX this service is updatable via, if any, for a service declared in the
+    /// VINTF manifest as coming from an APEX module.
+    fn updatable_via_apex(&mut self, name: &str) -> Result<Option<String16>>;
+
+    /// Returns debugging information -- name and pid -- about every service currently registered
+    /// with the service manager, for tools like `lshal --all --types=all`.
+    fn get_service_debug_info(&mut self) -> Result<Vec<ServiceDebugInfo>>;
+
+    /// Efficiently waits for a service to become available, blocking until it does, instead of
+    /// polling `check_service` in a loop.
+    ///
+    /// Implemented client-side on top of [`register_for_notifications`](Self::register_for_notifications)
+    /// rather than its own transaction: the service manager has no dedicated `waitForService`
+    /// call, so this registers a one-shot callback, blocks until it fires, then unregisters it.
+    fn wait_for_service(&mut self, name: &str) -> Result<SpIBinder>;
+
+    /// Registers `callback` to be invoked every time `name` is (re-)published, until a matching
+    /// [`unregister_for_notifications`](Self::unregister_for_notifications) call.
+    fn register_for_notifications<T: Binder + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        callback: &Service<T>,
+    ) -> Result<()>;
+
+    /// Stops a callback previously registered with
+    /// [`register_for_notifications`](Self::register_for_notifications) from receiving further
+    /// notifications.
+    fn unregister_for_notifications<T: Binder + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        callback: &Service<T>,
+    ) -> Result<()>;
 }
 
-impl IServiceManager for BpServiceManager {
-    fn get_service(&mut self, name: &str) -> Result<Interface> {
-        let mut data = Parcel::new();
-        unsafe {
-            data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+/// One entry of [`IServiceManager::get_service_debug_info`]: a registered service's name and the
+/// pid of the process that registered it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceDebugInfo {
+    pub name: String16,
+    pub debug_pid: i32,
+}
+
+/// A locally-hosted `android.os.IServiceCallback`: the service manager calls `on_registration`
+/// every time the service named in a matching
+/// [`register_for_notifications`](IServiceManager::register_for_notifications) call is
+/// (re-)published.
+pub trait IServiceCallback {
+    const INTERFACE_DESCRIPTOR: &'static str = "android.os.IServiceCallback";
+
+    /// Called with the freshly published `service` every time `name` is (re-)registered.
+    fn on_registration(&mut self, name: &str, service: SpIBinder) -> Result<()>;
+}
+
+/// Blanket impl so a plain closure can be passed directly to
+/// [`IServiceManager::register_for_notifications`] without a dedicated wrapper type.
+impl<F: FnMut(&str, SpIBinder) -> Result<()>> IServiceCallback for F {
+    fn on_registration(&mut self, name: &str, service: SpIBinder) -> Result<()> {
+        self(name, service)
+    }
+}
+
+/// Bridges a locally-implemented [`IServiceCallback`] into a binder [`Service<T>`] that
+/// `register_for_notifications` can send to the service manager.
+pub struct ServiceCallbackBinder<T: IServiceCallback>(Mutex<T>);
+
+impl<T: IServiceCallback> ServiceCallbackBinder<T> {
+    pub fn new(callback: T) -> Self {
+        Self(Mutex::new(callback))
+    }
+}
+
+impl<T: IServiceCallback> Binder for ServiceCallbackBinder<T> {
+    const INTERFACE_DESCRIPTOR: &'static str = T::INTERFACE_DESCRIPTOR;
+
+    fn on_transact(
+        &self,
+        code: TransactionCode,
+        data: &Parcel,
+        _reply: Option<&mut Parcel>,
+        _flags: TransactionFlags,
+    ) -> Result<()> {
+        match code {
+            // onRegistration(String name, IBinder service)
+            c if c == SpIBinder::FIRST_CALL_TRANSACTION => {
+                let name = data.read::<String16>()?.to_string();
+                let service = data.read::<SpIBinder>()?;
+                self.0.lock().unwrap().on_registration(&name, service)
+            }
+            _ => Ok(()),
         }
-        data.write_utf8_as_utf16(name)?;
-        let mut reply = Parcel::new();
-        self.0.transact(
-            Interface::FIRST_CALL_TRANSACTION + 0, // getService
-            &data,
-            Some(&mut reply),
+    }
+}
+
+/// Proxy for the system service manager.
+///
+/// Unlike most binder interfaces declared with [`declare_binder_interface!`], `IServiceManager`
+/// has no local (native) implementation in this process -- it's always the remote system service
+/// manager on the other end -- and its methods take `&mut self`, which the macro's
+/// `Arc<dyn Interface>`-backed native dispatch can't call through. So this proxy, and the
+/// [`Service<T>`]/[`Binder`] bridge `register_for_notifications` uses for callbacks, are written
+/// by hand instead.
+#[derive(Clone)]
+pub struct BpServiceManager(SpIBinder);
+
+impl Proxy for BpServiceManager {
+    fn get_descriptor() -> &'static str {
+        <Self as IServiceManager>::INTERFACE_DESCRIPTOR
+    }
+
+    fn from_binder(binder: SpIBinder) -> Result<Self> {
+        Ok(Self(binder))
+    }
+
+    fn as_binder(&self) -> SpIBinder {
+        self.0.clone()
+    }
+}
+
+impl IServiceManager for BpServiceManager {
+    fn get_service(&mut self, name: &str) -> Result<SpIBinder> {
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 0, // getService
             0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)
+            },
         )?;
         Status::from_parcel(&reply)?;
-        reply.read::<Interface>()
+        reply.read::<SpIBinder>()
     }
 
-    fn check_service(&mut self, name: &str) -> Result<Interface> {
-        let mut data = Parcel::new();
-        unsafe {
-            data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
-        }
-        data.write_utf8_as_utf16(name)?;
-        let mut reply = Parcel::new();
-        self.0.transact(
-            Interface::FIRST_CALL_TRANSACTION + 1, // checkService
-            &data,
-            Some(&mut reply),
+    fn check_service(&mut self, name: &str) -> Result<SpIBinder> {
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 1, // checkService
             0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)
+            },
         )?;
         Status::from_parcel(&reply)?;
-        reply.read::<Interface>()
+        reply.read::<SpIBinder>()
     }
 
-    fn add_service<T: Binder>(
+    fn add_service<T: Binder + Send + Sync + 'static>(
         &mut self,
         name: &str,
         service: &Service<T>,
         allow_isolated: bool,
         dump_priority: DumpFlags,
     ) -> Result<()> {
-        let mut data = Parcel::new();
-        unsafe {
-            data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
-        }
-        data.write_utf8_as_utf16(name)?;
-        data.write_service(service)?;
-        data.write_bool(allow_isolated)?;
-        data.write_i32(dump_priority as i32)?;
-        let mut reply = Parcel::new();
-        self.0.transact(
-            Interface::FIRST_CALL_TRANSACTION + 2, // addService
-            &data,
-            Some(&mut reply),
+        let reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 2, // addService
             0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)?;
+                data.write(&crate::interfaces::rpc::root_binder(service.clone()))?;
+                data.write_bool(allow_isolated)?;
+                data.write_i32(dump_priority as i32)
+            },
         )?;
         let status = Status::from_parcel(&reply)?;
         status.into()
     }
 
     fn list_services(&mut self, dump_priority: DumpFlags) -> Result<Vec<String16>> {
-        let mut data = Parcel::new();
-        unsafe {
-            data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
-        }
-        data.write_i32(dump_priority as i32)?;
-        let mut reply = Parcel::new();
-        self.0.transact(
-            Interface::FIRST_CALL_TRANSACTION + 3, // listServices
-            &data,
-            Some(&mut reply),
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 3, // listServices
             0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_i32(dump_priority as i32)
+            },
         )?;
         Status::from_parcel(&reply)?;
-
-        unimplemented!("need to implement readUtf8VectorFromUtf16Vector");
-        // reply.readUtf8VectorFromUtf16Vector()
+        reply.read_utf8_vector_from_utf16_vector()
     }
 
     fn is_declared(&mut self, name: &str) -> Result<bool> {
-        let mut data = Parcel::new();
-        unsafe {
-            data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
-        }
-        data.write_utf8_as_utf16(name)?;
-        let mut reply = Parcel::new();
-        self.0.transact(
-            Interface::FIRST_CALL_TRANSACTION + 6, // isDeclared
-            &data,
-            Some(&mut reply),
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 6, // isDeclared
             0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)
+            },
         )?;
         Status::from_parcel(&reply)?;
         reply.read_bool()
     }
+
+    fn get_declared_instances(&mut self, name: &str) -> Result<Vec<String16>> {
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 7, // getDeclaredInstances
+            0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)
+            },
+        )?;
+        Status::from_parcel(&reply)?;
+        reply.read_utf8_vector_from_utf16_vector()
+    }
+
+    fn updatable_via_apex(&mut self, name: &str) -> Result<Option<String16>> {
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 8, // updatableViaApex
+            0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)
+            },
+        )?;
+        Status::from_parcel(&reply)?;
+        reply.read::<Option<String16>>()
+    }
+
+    fn get_service_debug_info(&mut self) -> Result<Vec<ServiceDebugInfo>> {
+        let mut reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 12, // getServiceDebugInfo
+            0,
+            |data| unsafe { data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into()) },
+        )?;
+        Status::from_parcel(&reply)?;
+
+        let len = reply.read_i32()?;
+        if len < 0 {
+            return Ok(Vec::new());
+        }
+        // Don't pre-allocate `len` elements: `len` comes straight off the reply parcel, so a
+        // malformed/hostile reply could claim an enormous count before a single element has been
+        // validated against the parcel's actual remaining size. Grow the `Vec` organically
+        // instead; a bogus `len` just makes the loop below fail fast on the first short read.
+        let mut result = Vec::new();
+        for _ in 0..len {
+            let name = reply.read::<String16>()?;
+            let debug_pid = reply.read_i32()?;
+            result.push(ServiceDebugInfo { name, debug_pid });
+        }
+        Ok(result)
+    }
+
+    fn wait_for_service(&mut self, name: &str) -> Result<SpIBinder> {
+        if let Ok(service) = self.check_service(name) {
+            return Ok(service);
+        }
+
+        let found = Arc::new((Mutex::new(None::<SpIBinder>), Condvar::new()));
+        let found2 = Arc::clone(&found);
+        let callback = Service::new(ServiceCallbackBinder::new(move |_name: &str, service: SpIBinder| {
+            let (found, published) = &*found2;
+            *found.lock().unwrap() = Some(service);
+            published.notify_one();
+            Ok(())
+        }));
+
+        self.register_for_notifications(name, &callback)?;
+
+        let (found, published) = &*found;
+        let mut service = found.lock().unwrap();
+        while service.is_none() {
+            service = published.wait(service).unwrap();
+        }
+        let service = service.take().unwrap();
+
+        let _ = self.unregister_for_notifications(name, &callback);
+
+        Ok(service)
+    }
+
+    fn register_for_notifications<T: Binder + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        callback: &Service<T>,
+    ) -> Result<()> {
+        let reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 4, // registerForNotifications
+            0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)?;
+                data.write(&crate::interfaces::rpc::root_binder(callback.clone()))
+            },
+        )?;
+        let status = Status::from_parcel(&reply)?;
+        status.into()
+    }
+
+    fn unregister_for_notifications<T: Binder + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        callback: &Service<T>,
+    ) -> Result<()> {
+        let reply = self.0.transact_traced(
+            Self::INTERFACE_DESCRIPTOR,
+            SpIBinder::FIRST_CALL_TRANSACTION + 5, // unregisterForNotifications
+            0,
+            |data| {
+                unsafe {
+                    data.write_interface_token(&Self::INTERFACE_DESCRIPTOR.into())?;
+                }
+                data.write_utf8_as_utf16(name)?;
+                data.write(&crate::interfaces::rpc::root_binder(callback.clone()))
+            },
+        )?;
+        let status = Status::from_parcel(&reply)?;
+        status.into()
+    }
+}
+
+impl Parcel {
+    /// Reads a length-prefixed vector of UTF-16 strings, as written by repeated
+    /// `write_utf8_as_utf16` calls (the inverse of that operation). A `-1` length, matching a
+    /// null vector on the C++ side, decodes to an empty `Vec` rather than failing.
+    fn read_utf8_vector_from_utf16_vector(&mut self) -> Result<Vec<String16>> {
+        let len = self.read_i32()?;
+        if len < 0 {
+            return Ok(Vec::new());
+        }
+        // See the comment in `get_service_debug_info`: don't trust `len` for the allocation size,
+        // only for the loop bound.
+        let mut result = Vec::new();
+        for _ in 0..len {
+            result.push(self.read::<String16>()?);
+        }
+        Ok(result)
+    }
 }
 
 #[test]
@@ -173,7 +428,8 @@ fn test_check_service() {
 
 #[test]
 fn test_add_service() {
-    use crate::{Binder, Service, TransactionCode, TransactionFlags};
+    use crate::binder_impl::{TransactionCode, TransactionFlags};
+    use crate::interfaces::{Binder, Service};
 
     struct TestService;
 
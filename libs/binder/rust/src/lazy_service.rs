@@ -0,0 +1,132 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Client-activity callbacks for lazy services, letting a memory-constrained daemon notice when
+//! it has gone idle and exit instead of staying resident forever after
+//! [`register_lazy_service`](crate::register_lazy_service).
+
+use crate::unstable_api::AsNative;
+use crate::{sys, Result, SpIBinder, StatusCode};
+use std::ffi::{c_void, CString};
+use std::os::raw::c_char;
+
+extern "C" {
+    fn AServiceManager_registerLazyService(binder: *mut sys::AIBinder, instance: *const c_char) -> i32;
+    fn AServiceManager_registerClientCallback(
+        instance: *const c_char,
+        binder: *mut sys::AIBinder,
+        callback: extern "C" fn(has_clients: bool, cookie: *mut c_void),
+        cookie: *mut c_void,
+    );
+    /// Returns `true` if this process has no remaining clients across any of its lazy services and
+    /// the registrar has unregistered them all; `false` if a client raced in and unregistration was
+    /// aborted, in which case all services remain registered as before the call.
+    fn AServiceManager_tryUnregister() -> bool;
+    /// Re-registers every lazy service previously unregistered by [`AServiceManager_tryUnregister`],
+    /// for use after a failed shutdown attempt (e.g. the process decided not to exit after all).
+    fn AServiceManager_reRegister();
+}
+
+type ClientCallback = Box<dyn FnMut(bool) + Send>;
+
+/// A lazy service registered with an [`IClientCallback`][1], so it can learn when its client count
+/// rises from/falls to zero and decide for itself whether to exit while idle.
+///
+/// [1]: https://cs.android.com/android/platform/superproject/+/main:frameworks/native/libs/binder/include/binder/IClientCallback.h
+///
+/// This is the self-shutdown-capable counterpart to a plain
+/// [`register_lazy_service`](crate::register_lazy_service) call, which keeps the process resident
+/// for as long as it holds its binder handle.
+pub struct LazyServiceRegistrar {
+    instance: CString,
+}
+
+impl LazyServiceRegistrar {
+    /// The name this service was registered under.
+    pub fn name(&self) -> &str {
+        self.instance.to_str().expect("constructed from a Rust &str, so always valid UTF-8")
+    }
+
+    /// Attempts to unregister every lazy service this process has registered, as the very last
+    /// step of an idle shutdown. Returns `false` (and leaves every service registered) if a new
+    /// client connected while the attempt was in flight; the caller should treat that as a signal
+    /// to stay alive and keep serving, not retry the shutdown itself.
+    pub fn try_unregister(&self) -> bool {
+        // SAFETY: Takes no arguments and has no preconditions beyond this process having called
+        // `register_lazy_service`/`register_lazy_service_with_callback` at some point.
+        unsafe { AServiceManager_tryUnregister() }
+    }
+
+    /// Re-registers every lazy service that a prior [`try_unregister`](Self::try_unregister)
+    /// unregistered, for a process that decided not to exit after all (e.g. a new request arrived
+    /// between `try_unregister` returning and the process actually exiting).
+    pub fn re_register(&self) {
+        // SAFETY: Takes no arguments and has no preconditions beyond this process having called
+        // `try_unregister` at some point.
+        unsafe { AServiceManager_reRegister() };
+    }
+}
+
+/// Like [`register_lazy_service`](crate::register_lazy_service), but additionally registers an
+/// `IClientCallback` for `binder`: `on_clients` is invoked with `true` the moment the service gains
+/// its first client, and with `false` once its client count drops back to zero.
+///
+/// A service that wants to exit while idle should call
+/// [`LazyServiceRegistrar::try_unregister`] from its `on_clients(false)` branch, and
+/// [`LazyServiceRegistrar::re_register`] if that attempt succeeds but it then decides to keep
+/// running instead. A failed attempt leaves every service registered and needs no re-register.
+pub fn register_lazy_service_with_callback(
+    name: &str,
+    mut binder: SpIBinder,
+    on_clients: impl FnMut(bool) + Send + 'static,
+) -> Result<LazyServiceRegistrar> {
+    let instance = CString::new(name).map_err(|_| StatusCode::UNEXPECTED_NULL)?;
+
+    // SAFETY: `instance` is a valid, NUL-terminated C string for the duration of this call;
+    // `binder` is a valid AIBinder that the service manager takes its own strong reference to.
+    let status =
+        unsafe { AServiceManager_registerLazyService(binder.as_native_mut(), instance.as_ptr()) };
+    if status != 0 {
+        return Err(StatusCode::UNKNOWN_ERROR.into());
+    }
+
+    // Leaked for the life of the process: the service manager invokes this callback for as long
+    // as `instance` stays registered, which `try_unregister` never fully undoes (a failed
+    // unregister leaves the registration, and a successful one is either followed by process
+    // exit or undone by `re_register`).
+    let cookie: *mut ClientCallback = Box::into_raw(Box::new(Box::new(on_clients)));
+
+    // SAFETY: `instance` is a valid, NUL-terminated C string for the duration of this call;
+    // `binder` is the same AIBinder just registered above; `cookie` is a leaked `Box` that
+    // `on_clients_wrapper` borrows back on every invocation and never frees.
+    unsafe {
+        AServiceManager_registerClientCallback(
+            instance.as_ptr(),
+            binder.as_native_mut(),
+            on_clients_wrapper,
+            cookie.cast(),
+        );
+    }
+
+    Ok(LazyServiceRegistrar { instance })
+}
+
+extern "C" fn on_clients_wrapper(has_clients: bool, cookie: *mut c_void) {
+    let cb: *mut ClientCallback = cookie.cast();
+    // SAFETY: `cookie` is the `Box<ClientCallback>` leaked by `register_lazy_service_with_callback`
+    // above, which is never freed for the life of the process, so it is always valid here.
+    let cb = unsafe { &mut *cb };
+    cb(has_clients);
+}
@@ -96,15 +96,24 @@
 #[macro_use]
 mod binder;
 mod binder_async;
+mod binder_rpc;
 mod error;
+mod interfaces;
+mod lazy_service;
 mod native;
 mod parcel;
 mod proxy;
+mod record_replay;
+mod rpc;
+mod service_manager;
 mod state;
+mod transaction_trace;
 
 use binder_ndk_sys as sys;
 
 pub use crate::binder_async::{BinderAsyncPool, BoxFuture};
+pub use crate::binder_rpc::{RpcServer, RpcSession};
+pub use crate::lazy_service::{register_lazy_service_with_callback, LazyServiceRegistrar};
 pub use binder::{BinderFeatures, FromIBinder, IBinder, Interface, Strong, Weak};
 pub use error::{ExceptionCode, Status, StatusCode};
 pub use native::{
@@ -116,6 +125,7 @@ pub use proxy::{
     get_declared_instances, get_interface, get_service, is_declared, wait_for_interface,
     wait_for_service, DeathRecipient, SpIBinder, WpIBinder,
 };
+pub use record_replay::{replay_transactions, RecordedTransaction, RecordingId, ReplayMismatch};
 pub use state::{ProcessState, ThreadState};
 
 /// Binder result containing a [`Status`] on error.
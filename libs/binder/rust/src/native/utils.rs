@@ -272,17 +272,20 @@ impl PartialEq<&str> for String8 {
     }
 }
 
-// impl From<&[u16]> for String8 {
-//     fn from(s: &[u16]) -> Self {
-//         let mut string8 = MaybeUninit::uninit();
+impl From<&String16> for String8 {
+    /// Converts a `String16` (UTF-16) to a `String8` (UTF-8) using the
+    /// native transcoding constructor, avoiding a lossy/allocating round
+    /// trip through a Rust `String`.
+    fn from(s: &String16) -> Self {
+        let mut string8 = MaybeUninit::uninit();
 
-//         unsafe {
-//             android_String8_String87(string8.as_mut_ptr(), s.as_ptr(), s.len().try_into().unwrap());
-//         }
+        unsafe {
+            android_String8_String88(string8.as_mut_ptr(), s.0);
+        }
 
-//         String8(unsafe { string8.assume_init() })
-//     }
-// }
+        String8(unsafe { string8.assume_init() })
+    }
+}
 
 impl From<&str> for String8 {
     fn from(s: &str) -> Self {
@@ -367,6 +370,15 @@ impl From<&str> for String16 {
     }
 }
 
+impl From<&String8> for String16 {
+    /// Converts a `String8` (UTF-8) to a `String16` (UTF-16) using the
+    /// native transcoding constructor, avoiding a lossy/allocating round
+    /// trip through a Rust `String`.
+    fn from(s: &String8) -> Self {
+        unsafe { String16(android_String16_String168(&s.0)) }
+    }
+}
+
 impl From<&[u8]> for String16 {
     fn from(slice: &[u8]) -> Self {
         unsafe {
@@ -390,6 +402,42 @@ impl PartialEq for String16 {
     }
 }
 
+impl String8 {
+    /// Write this string to `parcel` using its native UTF-8 marshalling, so
+    /// it round-trips through a binder transaction without going through
+    /// `String16`.
+    pub fn write_to_parcel(&self, parcel: *mut android_Parcel) -> BinderResult<()> {
+        binder_status(unsafe { android_c_interface_Parcel_writeString8(parcel, &self.0) })
+    }
+
+    /// Read a `String8` back out of `parcel`, the inverse of
+    /// [`String8::write_to_parcel`].
+    pub fn read_from_parcel(parcel: *const android_Parcel) -> BinderResult<String8> {
+        let mut string8 = String8::new();
+        binder_status(unsafe {
+            android_c_interface_Parcel_readString8(parcel, &mut string8.0)
+        })?;
+        Ok(string8)
+    }
+}
+
+impl String16 {
+    /// Write this string to `parcel` using its native UTF-16 marshalling.
+    pub fn write_to_parcel(&self, parcel: *mut android_Parcel) -> BinderResult<()> {
+        binder_status(unsafe { android_c_interface_Parcel_writeString16(parcel, self.0) })
+    }
+
+    /// Read a `String16` back out of `parcel`, the inverse of
+    /// [`String16::write_to_parcel`].
+    pub fn read_from_parcel(parcel: *const android_Parcel) -> BinderResult<String16> {
+        let mut string16 = String16::new();
+        binder_status(unsafe {
+            android_c_interface_Parcel_readString16(parcel, &mut string16.0)
+        })?;
+        Ok(string16)
+    }
+}
+
 impl fmt::Debug for android_IBinder {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         "IBinder".fmt(f)
@@ -0,0 +1,155 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! [`ParcelFileDescriptor`]: an owned file descriptor that can cross a binder transaction,
+//! mirroring AOSP's `android.os.ParcelFileDescriptor`.
+
+use crate::binder_impl::{BorrowedParcel, Deserialize, DeserializeOption, Serialize, SerializeOption};
+use crate::unstable_api::AsNative;
+use crate::{sys, Result, StatusCode};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+
+/// An owned file descriptor that can be written into or read out of a binder `Parcel`, for
+/// passing things like shared memory regions or sockets across a transaction.
+///
+/// Writing one dups the underlying fd into the transaction; the original stays open and owned by
+/// the caller. Reading one out of a parcel hands back a dup'd fd the receiver owns outright, so
+/// this type's `Drop` (via the underlying `OwnedFd`) is always correct to run.
+#[derive(Debug)]
+pub struct ParcelFileDescriptor(OwnedFd);
+
+impl ParcelFileDescriptor {
+    /// Takes ownership of `fd`.
+    pub fn new(fd: OwnedFd) -> Self {
+        Self(fd)
+    }
+}
+
+impl AsRawFd for ParcelFileDescriptor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for ParcelFileDescriptor {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl Serialize for ParcelFileDescriptor {
+    fn serialize(&self, parcel: &mut BorrowedParcel<'_>) -> Result<()> {
+        // SAFETY: `parcel` wraps a valid, writable AParcel; `self.0` is a valid, open fd that the
+        // NDK dups into the parcel rather than taking ownership of.
+        let status = unsafe {
+            sys::AParcel_writeParcelFileDescriptor(parcel.as_native_mut(), self.0.as_raw_fd())
+        };
+        status_result(status)
+    }
+}
+
+impl Deserialize for ParcelFileDescriptor {
+    fn deserialize(parcel: &BorrowedParcel<'_>) -> Result<Self> {
+        let mut fd: RawFd = -1;
+        // SAFETY: `parcel` wraps a valid, readable AParcel; `fd` is an out-parameter the NDK
+        // fills with a dup'd, owned fd on success.
+        let status = unsafe { sys::AParcel_readParcelFileDescriptor(parcel.as_native(), &mut fd) };
+        status_result(status)?;
+        // SAFETY: The NDK just handed us ownership of a valid, open fd.
+        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) }))
+    }
+}
+
+impl SerializeOption for ParcelFileDescriptor {
+    fn serialize_option(this: Option<&Self>, parcel: &mut BorrowedParcel<'_>) -> Result<()> {
+        match this {
+            Some(fd) => fd.serialize(parcel),
+            None => {
+                // SAFETY: `parcel` wraps a valid, writable AParcel; `-1` is the NDK's documented
+                // encoding for a null `ParcelFileDescriptor`.
+                let status =
+                    unsafe { sys::AParcel_writeParcelFileDescriptor(parcel.as_native_mut(), -1) };
+                status_result(status)
+            }
+        }
+    }
+}
+
+impl DeserializeOption for ParcelFileDescriptor {
+    fn deserialize_option(parcel: &BorrowedParcel<'_>) -> Result<Option<Self>> {
+        let mut fd: RawFd = -1;
+        // SAFETY: `parcel` wraps a valid, readable AParcel; `fd` is an out-parameter the NDK
+        // fills with either a dup'd, owned fd or `-1` for a null `ParcelFileDescriptor`.
+        let status = unsafe { sys::AParcel_readParcelFileDescriptor(parcel.as_native(), &mut fd) };
+        status_result(status)?;
+        if fd < 0 {
+            Ok(None)
+        } else {
+            // SAFETY: The NDK just handed us ownership of a valid, open fd.
+            Ok(Some(Self(unsafe { OwnedFd::from_raw_fd(fd) })))
+        }
+    }
+}
+
+fn status_result(status: i32) -> Result<()> {
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(StatusCode::UNKNOWN_ERROR.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binder_impl::Parcel;
+
+    extern "C" {
+        fn pipe(fds: *mut RawFd) -> i32;
+        fn write(fd: RawFd, buf: *const u8, count: usize) -> isize;
+        fn read(fd: RawFd, buf: *mut u8, count: usize) -> isize;
+    }
+
+    #[test]
+    fn round_trips_an_owned_fd_and_a_null_fd() {
+        let mut fds = [-1 as RawFd; 2];
+        // SAFETY: `fds` is a valid, 2-element buffer for `pipe` to fill in.
+        assert_eq!(unsafe { pipe(fds.as_mut_ptr()) }, 0);
+        let [read_fd, write_fd] = fds;
+        // SAFETY: `write_fd` is the valid write end `pipe` just returned.
+        assert_eq!(unsafe { write(write_fd, b"hi".as_ptr(), 2) }, 2);
+        // SAFETY: `read_fd`/`write_fd` are the valid, open fds `pipe` just returned.
+        let read_fd = ParcelFileDescriptor::new(unsafe { OwnedFd::from_raw_fd(read_fd) });
+        let write_fd = unsafe { OwnedFd::from_raw_fd(write_fd) };
+
+        let mut parcel = Parcel::new();
+        parcel.write(&read_fd).unwrap();
+        parcel.write(&None::<ParcelFileDescriptor>).unwrap();
+        // SAFETY: 0 is always a valid position.
+        unsafe { parcel.set_data_position(0).unwrap() };
+
+        let received_fd = parcel.read::<ParcelFileDescriptor>().unwrap();
+        let received_none = parcel.read::<Option<ParcelFileDescriptor>>().unwrap();
+        assert!(received_none.is_none());
+
+        let mut buf = [0u8; 2];
+        // SAFETY: `received_fd` is the dup'd read end the parcel just handed back; `buf` is a
+        // valid 2-byte buffer.
+        assert_eq!(unsafe { read(received_fd.as_raw_fd(), buf.as_mut_ptr(), buf.len()) }, 2);
+        assert_eq!(&buf, b"hi");
+
+        drop(write_fd);
+    }
+}
@@ -0,0 +1,44 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Constructor for a [`Parcel`] backed directly by a raw byte buffer, bypassing the binder
+//! transaction that normally produces one. Used by the fuzz target and by `ParcelableHolder`,
+//! which keeps not-yet-decoded contents as marshaled bytes.
+
+use crate::binder_impl::Parcel;
+use crate::sys;
+use crate::unstable_api::AsNative;
+
+impl Parcel {
+    /// Builds a `Parcel` whose contents are exactly `bytes`, without going through a real binder
+    /// transaction.
+    ///
+    /// `bytes` need not have come from a prior `Parcel`'s own marshaled output -- this is the
+    /// entry point the `parcel_deserialize` fuzz target uses to drive arbitrary, possibly
+    /// malformed, byte buffers through the typed `read::<T>()` paths. A buffer the NDK considers
+    /// malformed simply makes later reads fail with a `StatusCode`, not a panic.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut parcel = Parcel::new();
+        // SAFETY: `parcel` wraps a freshly created, empty, writable AParcel; `bytes` is a valid
+        // buffer of its own length. Ignoring the returned status is deliberate: even a buffer the
+        // NDK rejects at unmarshal time should leave later `read::<T>()` calls to fail cleanly
+        // rather than operate on uninitialized data, which is exactly the invariant this
+        // constructor exists to let the fuzz target exercise.
+        unsafe {
+            sys::AParcel_unmarshal(parcel.as_native_mut(), bytes.as_ptr(), bytes.len() as i32);
+        }
+        parcel
+    }
+}
@@ -17,12 +17,27 @@
 use crate::binder::Stability;
 use crate::error::StatusCode;
 use crate::parcel::{Parcel, Parcelable};
-use crate::{impl_deserialize_for_parcelable, impl_serialize_for_parcelable};
+use crate::unstable_api::AsNative;
+use crate::{impl_deserialize_for_parcelable, impl_serialize_for_parcelable, sys};
 
-use downcast_rs::{impl_downcast, Downcast};
+use downcast_rs::{impl_downcast, DowncastSync};
 use std::any::Any;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// Marshals `parcel`'s current contents into an owned byte buffer.
+///
+/// Unlike a [`Parcel`], which wraps a raw `AParcel` pointer and is neither `Send` nor `Sync`, the
+/// returned `Vec<u8>` can be stashed in [`ParcelableHolderData`] across threads and turned back
+/// into a `Parcel` on whichever thread eventually decodes it, via [`Parcel::from_bytes`].
+fn marshal(parcel: &Parcel) -> Vec<u8> {
+    // SAFETY: `parcel` wraps a valid AParcel for the duration of this call.
+    let len = unsafe { sys::AParcel_getDataSize(parcel.as_native()) };
+    let mut bytes = vec![0u8; len.max(0) as usize];
+    // SAFETY: `parcel` wraps a valid AParcel; `bytes` is a buffer of exactly `len` bytes, the
+    // full size the NDK just reported for this parcel.
+    unsafe { sys::AParcel_marshal(parcel.as_native(), bytes.as_mut_ptr(), 0, len) };
+    bytes
+}
 
 /// Metadata that `ParcelableHolder` needs for all parcelables.
 ///
@@ -40,18 +55,21 @@ pub trait ParcelableMetadata {
     }
 }
 
-trait AnyParcelable: Downcast + Parcelable + std::fmt::Debug {}
-impl_downcast!(AnyParcelable);
-impl<T> AnyParcelable for T where T: Downcast + Parcelable + std::fmt::Debug {}
+trait AnyParcelable: DowncastSync + Parcelable + std::fmt::Debug {}
+impl_downcast!(sync AnyParcelable);
+impl<T> AnyParcelable for T where T: DowncastSync + Parcelable + std::fmt::Debug {}
 
 #[derive(Debug, Clone)]
 enum ParcelableHolderData {
     Empty,
     Parcelable {
-        parcelable: Rc<dyn AnyParcelable>,
+        parcelable: Arc<dyn AnyParcelable>,
         name: String,
     },
-    Parcel(Parcel),
+    /// Not-yet-decoded parcelable bytes read off the wire before the concrete type `T` is known.
+    /// Kept as a marshaled buffer rather than a live [`Parcel`] so this variant doesn't strip
+    /// `ParcelableHolderData` (and therefore `ParcelableHolder`) of `Send`/`Sync`.
+    Raw(Vec<u8>),
 }
 
 impl Default for ParcelableHolderData {
@@ -64,26 +82,35 @@ impl Default for ParcelableHolderData {
 ///
 /// This type is currently used for AIDL parcelable fields.
 ///
-/// `ParcelableHolder` is currently not thread-safe (neither
-/// `Send` nor `Sync`), mainly because it internally contains
-/// a `Parcel` which in turn is not thread-safe.
-#[derive(Debug, Default, Clone)]
+/// `ParcelableHolder` is `Send + Sync`: it stores its contents behind a `Mutex` rather than a
+/// `RefCell`, requires its parcelables to be `Send + Sync` too, and keeps not-yet-decoded wire
+/// data as a plain byte buffer rather than a live `Parcel` (which wraps a raw `AParcel` pointer
+/// and is neither). So a holder (and any AIDL parcelable containing one) can cross `.await`
+/// points or be moved onto a `spawn_blocking` thread when used from async binder service
+/// implementations.
+#[derive(Debug, Default)]
 pub struct ParcelableHolder {
-    // This is a `RefCell` because of `get_parcelable`
+    // This is a `Mutex` because of `get_parcelable`
     // which takes `&self` for consistency with C++.
     // We could make `get_parcelable` take a `&mut self`
-    // and get rid of the `RefCell` here for a performance
+    // and get rid of the `Mutex` here for a performance
     // improvement, but then callers would require a mutable
     // `ParcelableHolder` even for that getter method.
-    data: RefCell<ParcelableHolderData>,
+    data: Mutex<ParcelableHolderData>,
     stability: Stability,
 }
 
+impl Clone for ParcelableHolder {
+    fn clone(&self) -> Self {
+        Self { data: Mutex::new(self.data.lock().unwrap().clone()), stability: self.stability }
+    }
+}
+
 impl ParcelableHolder {
     /// Construct a new `ParcelableHolder` with the given stability.
     pub fn new(stability: Stability) -> Self {
         Self {
-            data: RefCell::new(ParcelableHolderData::Empty),
+            data: Mutex::new(ParcelableHolderData::Empty),
             stability,
         }
     }
@@ -93,20 +120,20 @@ impl ParcelableHolder {
     /// Note that this method does not reset the stability,
     /// only the contents.
     pub fn reset(&mut self) {
-        *self.data.get_mut() = ParcelableHolderData::Empty;
+        *self.data.get_mut().unwrap() = ParcelableHolderData::Empty;
         // We could also clear stability here, but C++ doesn't
     }
 
     /// Set the parcelable contained in this `ParcelableHolder`.
-    pub fn set_parcelable<T>(&mut self, p: Rc<T>) -> Result<(), StatusCode>
+    pub fn set_parcelable<T>(&mut self, p: Arc<T>) -> Result<(), StatusCode>
     where
-        T: Any + Parcelable + ParcelableMetadata + std::fmt::Debug,
+        T: Any + Parcelable + ParcelableMetadata + Send + Sync + std::fmt::Debug,
     {
         if self.stability > p.get_stability() {
             return Err(StatusCode::BAD_VALUE);
         }
 
-        *self.data.get_mut() = ParcelableHolderData::Parcelable {
+        *self.data.get_mut().unwrap() = ParcelableHolderData::Parcelable {
             parcelable: p,
             name: T::get_descriptor().into(),
         };
@@ -127,12 +154,12 @@ impl ParcelableHolder {
     /// * `Ok(None)` if the holder is empty or the descriptor does not match
     /// * `Ok(Some(_))` if the object holds a parcelable of type `T`
     ///   with the correct descriptor
-    pub fn get_parcelable<T>(&self) -> Result<Option<Rc<T>>, StatusCode>
+    pub fn get_parcelable<T>(&self) -> Result<Option<Arc<T>>, StatusCode>
     where
-        T: Any + Parcelable + ParcelableMetadata + Default + std::fmt::Debug,
+        T: Any + Parcelable + ParcelableMetadata + Default + Send + Sync + std::fmt::Debug,
     {
         let parcelable_desc = T::get_descriptor();
-        let mut data = self.data.borrow_mut();
+        let mut data = self.data.lock().unwrap();
         match *data {
             ParcelableHolderData::Empty => Ok(None),
             ParcelableHolderData::Parcelable {
@@ -143,12 +170,13 @@ impl ParcelableHolder {
                     return Err(StatusCode::BAD_VALUE);
                 }
 
-                match Rc::clone(parcelable).downcast_rc::<T>() {
+                match Arc::clone(parcelable).downcast_arc::<T>() {
                     Err(_) => Err(StatusCode::BAD_VALUE),
                     Ok(x) => Ok(Some(x)),
                 }
             }
-            ParcelableHolderData::Parcel(ref parcel) => {
+            ParcelableHolderData::Raw(ref bytes) => {
+                let parcel = Parcel::from_bytes(bytes);
                 unsafe {
                     // Safety: 0 should always be a valid position.
                     parcel.set_data_position(0)?;
@@ -160,10 +188,10 @@ impl ParcelableHolder {
                 }
 
                 let mut parcelable = T::default();
-                parcelable.read_from_parcel(parcel)?;
+                parcelable.read_from_parcel(&parcel)?;
 
-                let parcelable = Rc::new(parcelable);
-                let result = Rc::clone(&parcelable);
+                let parcelable = Arc::new(parcelable);
+                let result = Arc::clone(&parcelable);
                 *data = ParcelableHolderData::Parcelable { parcelable, name };
 
                 Ok(Some(result))
@@ -184,7 +212,7 @@ impl Parcelable for ParcelableHolder {
     fn write_to_parcel(&self, parcel: &mut Parcel) -> Result<(), StatusCode> {
         parcel.write(&self.stability)?;
 
-        match *self.data.borrow() {
+        match *self.data.lock().unwrap() {
             ParcelableHolderData::Empty => parcel.write(&0i32),
             ParcelableHolderData::Parcelable {
                 ref parcelable,
@@ -212,9 +240,9 @@ impl Parcelable for ParcelableHolder {
 
                 Ok(())
             }
-            ParcelableHolderData::Parcel(ref p) => {
-                parcel.write(&p.get_data_size())?;
-                parcel.append_all_from(p)
+            ParcelableHolderData::Raw(ref bytes) => {
+                parcel.write(&(bytes.len() as i32))?;
+                parcel.append_all_from(&Parcel::from_bytes(bytes))
             }
         }
     }
@@ -229,7 +257,7 @@ impl Parcelable for ParcelableHolder {
             return Err(StatusCode::BAD_VALUE);
         }
         if data_size == 0 {
-            *self.data.get_mut() = ParcelableHolderData::Empty;
+            *self.data.get_mut().unwrap() = ParcelableHolderData::Empty;
             return Ok(());
         }
 
@@ -242,7 +270,7 @@ impl Parcelable for ParcelableHolder {
 
         let mut new_parcel = Parcel::new();
         new_parcel.append_from(parcel, data_start, data_size)?;
-        *self.data.get_mut() = ParcelableHolderData::Parcel(new_parcel);
+        *self.data.get_mut().unwrap() = ParcelableHolderData::Raw(marshal(&new_parcel));
 
         unsafe {
             // Safety: `append_from` checks if `data_size` overflows
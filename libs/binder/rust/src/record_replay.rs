@@ -0,0 +1,308 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Records binder transactions to a flat file and replays them against a live binder, mirroring
+//! libbinder's native `RecordedTransaction`.
+//!
+//! Call [`SpIBinder::start_recording`] to begin appending every transaction sent through
+//! [`SpIBinder::transact_recorded`] to a file, and [`replay_transactions`] to later re-issue a
+//! recorded corpus against a (possibly different) live binder and diff the replies. The recorded
+//! file doubles as a seed corpus for fuzzing a service's `on_transact`.
+
+use crate::binder_impl::{Parcel, TransactionCode, TransactionFlags};
+use crate::unstable_api::AsNative;
+use crate::{sys, IBinder, Result, SpIBinder, StatusCode};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Magic bytes and format version at the start of every recording, so [`replay_transactions`]
+/// can reject files produced by an incompatible version up front.
+const RECORDING_MAGIC: &[u8; 4] = b"RRT1";
+
+/// One recorded transaction: enough to both replay it against a live binder and to seed a fuzzer
+/// for the service's `on_transact`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedTransaction {
+    /// The interface descriptor of the binder the transaction was sent to, as supplied by the
+    /// caller of [`SpIBinder::transact_recorded`].
+    pub interface_descriptor: String,
+    /// The transaction code, e.g. `SpIBinder::FIRST_CALL_TRANSACTION + N`.
+    pub code: TransactionCode,
+    /// The flags the transaction was sent with, e.g. `SpIBinder::FLAG_ONEWAY`.
+    pub flags: TransactionFlags,
+    /// The raw, marshaled bytes of the request parcel.
+    pub request: Vec<u8>,
+    /// The raw, marshaled bytes of the reply parcel, or `None` for a oneway transaction, which
+    /// has no reply frame, and for a transaction that failed (`status_ok` is `false`).
+    pub reply: Option<Vec<u8>>,
+    /// Whether the transaction completed successfully. When `false`, [`status_description`]
+    /// holds `Status`'s debug-formatted description, since `Status` doesn't expose a stable
+    /// numeric `status_t` through its safe API.
+    ///
+    /// [`status_description`]: RecordedTransaction::status_description
+    pub status_ok: bool,
+    /// `Status`'s debug-formatted description when `status_ok` is `false`; `None` on success.
+    pub status_description: Option<String>,
+}
+
+impl RecordedTransaction {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write_frame(out, self.interface_descriptor.as_bytes())?;
+        out.write_all(&self.code.to_le_bytes())?;
+        out.write_all(&self.flags.to_le_bytes())?;
+        out.write_all(&[self.status_ok as u8])?;
+        write_frame(out, self.status_description.as_deref().unwrap_or("").as_bytes())?;
+        write_frame(out, &self.request)?;
+        match &self.reply {
+            Some(reply) => {
+                out.write_all(&[1u8])?;
+                write_frame(out, reply)?;
+            }
+            None => out.write_all(&[0u8])?,
+        }
+        Ok(())
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Option<Self>> {
+        let interface_descriptor = match read_frame(input)? {
+            Some(bytes) => String::from_utf8(bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            None => return Ok(None),
+        };
+        let code = read_u32(input)?;
+        let flags = read_u32(input)?;
+        let status_ok = read_u8(input)? != 0;
+        let status_description = read_frame(input)?
+            .ok_or_else(unexpected_eof)
+            .and_then(|bytes| String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+            .map(|s| (!s.is_empty()).then_some(s))?;
+        let request = read_frame(input)?.ok_or_else(unexpected_eof)?;
+        let reply = match read_u8(input)? {
+            0 => None,
+            _ => Some(read_frame(input)?.ok_or_else(unexpected_eof)?),
+        };
+        Ok(Some(Self {
+            interface_descriptor,
+            code,
+            flags,
+            request,
+            reply,
+            status_ok,
+            status_description,
+        }))
+    }
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated transaction record")
+}
+
+fn write_frame(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+fn read_frame(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len_bytes = [0u8; 4];
+    match input.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    input.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0u8; 1];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Marshals `parcel`'s current contents into a flat byte buffer, for storage in a
+/// [`RecordedTransaction`]. Binder/FD objects embedded in the parcel are marshaled as whatever
+/// opaque placeholder the NDK emits for them, since they cannot be faithfully serialized across
+/// processes; [`replay_transactions`] surfaces the resulting reply mismatch as a warning rather
+/// than failing outright.
+fn marshal(parcel: &Parcel) -> Vec<u8> {
+    // SAFETY: `parcel` wraps a valid AParcel for the duration of this call.
+    let len = unsafe { sys::AParcel_getDataSize(parcel.as_native()) };
+    let mut bytes = vec![0u8; len.max(0) as usize];
+    // SAFETY: `parcel` wraps a valid AParcel; `bytes` is a buffer of exactly `len` bytes, the
+    // full size the NDK just reported for this parcel.
+    unsafe { sys::AParcel_marshal(parcel.as_native(), bytes.as_mut_ptr(), 0, len) };
+    bytes
+}
+
+/// Identifies one [`start_recording`](SpIBinder::start_recording) session. A monotonically
+/// increasing counter rather than the recorded binder's native pointer: a dropped `SpIBinder`'s
+/// address can be handed out to an unrelated binder by the allocator, which would otherwise let
+/// that new binder's transactions silently land in the first binder's stale recording file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecordingId(usize);
+
+static NEXT_RECORDING_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Active recorders, keyed by [`RecordingId`].
+static RECORDERS: Mutex<Option<HashMap<RecordingId, Mutex<File>>>> = Mutex::new(None);
+
+impl SpIBinder {
+    /// Starts recording every transaction subsequently sent through
+    /// [`transact_recorded`](Self::transact_recorded) with the returned [`RecordingId`] to `path`,
+    /// truncating it if it already exists.
+    pub fn start_recording(&self, path: impl AsRef<Path>) -> io::Result<RecordingId> {
+        let mut file = OpenOptions::new().create(true).write(true).truncate(true).open(path)?;
+        file.write_all(RECORDING_MAGIC)?;
+        let id = RecordingId(NEXT_RECORDING_ID.fetch_add(1, Ordering::Relaxed));
+        RECORDERS.lock().unwrap().get_or_insert_with(HashMap::new).insert(id, Mutex::new(file));
+        Ok(id)
+    }
+
+    /// Stops the recording session `id`, flushing and closing the file started by
+    /// [`start_recording`](Self::start_recording). A no-op if `id` isn't an active session.
+    pub fn stop_recording(&self, id: RecordingId) {
+        if let Some(recorders) = RECORDERS.lock().unwrap().as_mut() {
+            recorders.remove(&id);
+        }
+    }
+
+    /// Like [`IBinder::transact`], but additionally appends a [`RecordedTransaction`] for this
+    /// call to the file started by [`start_recording`](Self::start_recording) under `id`, if that
+    /// session is still active. `interface_descriptor` identifies the interface being called,
+    /// e.g. a generated AIDL proxy's `Interface::get_descriptor()`.
+    pub fn transact_recorded<F: FnOnce(&mut Parcel) -> Result<()>>(
+        &self,
+        id: RecordingId,
+        interface_descriptor: &str,
+        code: TransactionCode,
+        flags: TransactionFlags,
+        input_callback: F,
+    ) -> Result<Parcel> {
+        let mut request_bytes = Vec::new();
+        let result = self.transact(code, flags, |parcel| {
+            let status = input_callback(parcel);
+            request_bytes = marshal(parcel);
+            status
+        });
+
+        if let Some(Some(file)) =
+            RECORDERS.lock().unwrap().as_ref().map(|recorders| recorders.get(&id))
+        {
+            let (reply, status_ok, status_description) = match &result {
+                Ok(reply) => {
+                    let reply = (flags & Self::FLAG_ONEWAY == 0).then(|| marshal(reply));
+                    (reply, true, None)
+                }
+                Err(status) => (None, false, Some(format!("{:?}", status))),
+            };
+            let record = RecordedTransaction {
+                interface_descriptor: interface_descriptor.to_string(),
+                code,
+                flags,
+                request: request_bytes,
+                reply,
+                status_ok,
+                status_description,
+            };
+            if let Ok(mut file) = file.lock() {
+                let _ = record.write_to(&mut *file);
+            }
+        }
+
+        result
+    }
+}
+
+/// One mismatch found by [`replay_transactions`] between a recorded reply and the reply a live
+/// binder actually returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayMismatch {
+    /// Index of the mismatched transaction within the recorded file.
+    pub index: usize,
+    /// The recorded transaction's code.
+    pub code: TransactionCode,
+    /// What differed between the recorded and replayed reply.
+    pub reason: String,
+}
+
+/// Re-issues every transaction in the file at `path` against `binder`, diffing each reply
+/// against the one that was recorded. Oneway transactions and transactions that failed when
+/// recorded have no recorded reply; they are replayed but never diffed.
+pub fn replay_transactions(
+    binder: &SpIBinder,
+    path: impl AsRef<Path>,
+) -> io::Result<Vec<ReplayMismatch>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != RECORDING_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "unrecognized recording format"));
+    }
+
+    let mut mismatches = Vec::new();
+    let mut index = 0;
+    while let Some(record) = RecordedTransaction::read_from(&mut file)? {
+        let replayed = binder.transact(record.code, record.flags, |parcel| {
+            // SAFETY: `parcel` wraps a valid AParcel that is currently empty (freshly handed to
+            // us by `transact`); `record.request` is the exact byte count a prior `marshal` call
+            // reported for an equivalent parcel.
+            let status = unsafe {
+                sys::AParcel_unmarshal(
+                    parcel.as_native_mut(),
+                    record.request.as_ptr(),
+                    record.request.len() as i32,
+                )
+            };
+            if status == 0 {
+                Ok(())
+            } else {
+                Err(StatusCode::UNKNOWN_ERROR.into())
+            }
+        });
+
+        match (&replayed, &record.reply) {
+            (Ok(reply), Some(expected)) => {
+                if marshal(reply) != *expected {
+                    mismatches.push(ReplayMismatch {
+                        index,
+                        code: record.code,
+                        reason: "reply bytes differ from recording".to_string(),
+                    });
+                }
+            }
+            (Err(status), Some(_)) => {
+                mismatches.push(ReplayMismatch {
+                    index,
+                    code: record.code,
+                    reason: format!("replay failed with {:?}, expected a reply", status),
+                });
+            }
+            (_, None) => {}
+        }
+        index += 1;
+    }
+    Ok(mismatches)
+}
@@ -0,0 +1,87 @@
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, unused)]
+
+use crate::error::{Result, StatusCode};
+use crate::proxy::Interface;
+use crate::sys::libbinder_bindings::*;
+use crate::utils::{AsNative, Sp};
+
+use std::os::raw::c_int;
+
+/// Client session for connecting to a remote binder service over a socket
+/// transport (e.g. VSOCK, a Unix domain socket, or a pre-connected file
+/// descriptor) instead of the kernel `/dev/binder` driver.
+///
+/// This is the Rust equivalent of libbinder's `RpcSession`, and is the
+/// building block used by binder-over-sockets consumers such as microdroid
+/// and other VM guest/host use cases.
+wrap_sp! {
+    pub struct RpcSession(Sp<android_RpcSession>) {
+        getter: android_c_interface_Sp_getRpcSession,
+        destructor: android_c_interface_Sp_DropRpcSession,
+        clone: android_c_interface_Sp_CloneRpcSession,
+    }
+}
+
+impl RpcSession {
+    /// Create a new, unconnected `RpcSession`.
+    pub fn new() -> Self {
+        unsafe { Self(Sp(android_c_interface_NewRpcSession())) }
+    }
+
+    /// Set the maximum number of incoming threads the session's thread pool
+    /// may use to process transactions from the remote peer.
+    ///
+    /// Must be called before connecting.
+    pub fn set_max_incoming_threads(&self, threads: usize) -> &Self {
+        unsafe {
+            android_c_interface_RpcSession_setMaxIncomingThreads(
+                self.0.as_native(),
+                threads as c_int,
+            );
+        }
+        self
+    }
+
+    /// Set the maximum number of outgoing connections the session may open
+    /// to the remote peer.
+    ///
+    /// Must be called before connecting.
+    pub fn set_max_outgoing_connections(&self, connections: usize) -> &Self {
+        unsafe {
+            android_c_interface_RpcSession_setMaxOutgoingConnections(
+                self.0.as_native(),
+                connections as c_int,
+            );
+        }
+        self
+    }
+
+    /// Connect to a server listening on the given Unix domain socket path,
+    /// returning the server's root object.
+    pub unsafe fn setup_unix_domain_client(&self, path: &str) -> Result<Option<Interface>> {
+        let path = std::ffi::CString::new(path).map_err(|_| StatusCode::UNEXPECTED_NULL)?;
+        Ok(Interface::from_raw(android_c_interface_RpcSession_setupUnixDomainClient(
+            self.0.as_native(),
+            path.as_ptr(),
+        )))
+    }
+
+    /// Connect to a server listening on the given VSOCK CID/port, returning
+    /// the server's root object.
+    pub unsafe fn setup_vsock_client(&self, cid: u32, port: u32) -> Option<Interface> {
+        Interface::from_raw(android_c_interface_RpcSession_setupVsockClient(
+            self.0.as_native(),
+            cid,
+            port,
+        ))
+    }
+
+    /// Connect using an already-connected socket file descriptor, returning
+    /// the server's root object.
+    pub unsafe fn setup_preconnected_client(&self, fd: std::os::raw::c_int) -> Option<Interface> {
+        Interface::from_raw(android_c_interface_RpcSession_setupPreconnectedClient(
+            self.0.as_native(),
+            fd,
+        ))
+    }
+}
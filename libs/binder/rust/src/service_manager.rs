@@ -1,9 +1,11 @@
 #![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, unused)]
 
+use crate::error::{binder_status, BinderResult};
 use crate::proxy::Interface;
 use crate::sys::libbinder_bindings::*;
 use crate::utils::{AsNative, Sp, Str16, String16};
 
+use std::convert::TryInto;
 use std::mem::MaybeUninit;
 use std::os::raw::c_int;
 use std::ptr;
@@ -21,6 +23,7 @@ wrap_sp! {
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy)]
 pub enum DumpFlags {
     // Must match values in IServiceManager.aidl
     /// Allows services to dump sections according to priorities.
@@ -50,12 +53,7 @@ impl IServiceManager {
     pub const DUMP_FLAG_PRIORITY_ALL: c_int = android_IServiceManager_DUMP_FLAG_PRIORITY_ALL;
     pub const DUMP_FLAG_PROTO: c_int = android_IServiceManager_DUMP_FLAG_PROTO;
 
-    /// Return list of all existing services.
-    // pub unsafe fn listServices(&self, dumpsysFlags: c_int) -> Vec<String16> {
-    //     IServiceManager_listServices(self.0)
-    // }
-
-    // // for ABI compatibility
+    // for ABI compatibility
     pub unsafe fn getInterfaceDescriptor(&self) -> &Str16 {
         Str16::from_ptr(android_c_interface_IServiceManager_getInterfaceDescriptor(
             self.0.as_native(),
@@ -71,42 +69,212 @@ impl IServiceManager {
         ))
     }
 
-    // /// Retrieve an existing service, non-blocking.
-    // pub unsafe fn checkService(&self, name: &String16) -> Sp<IBinder> {
-    //     let mut sm = Sp::null();
-    //     (*self.vtable).checkService.unwrap()(&mut sm, self, name);
-    //     sm
-    // }
-
-    // /// Register a service.
-    // pub unsafe fn addService(
-    //     &self,
-    //     name: &String16,
-    //     service: *const Sp<IBinder>,
-    //     allowIsolated: bool,
-    //     dumpsysFlags: i32,
-    // ) -> android_status_t {
-    //     (*self.vtable).addService.unwrap()(self, name, service, allowIsolated, dumpsysFlags)
-    // }
-
-    // /// Efficiently wait for a service.
-    // ///
-    // /// Returns nullptr only for permission problem or fatal error.
-    // pub unsafe fn waitForService(&self, name: &String16) -> Sp<IBinder> {
-    //     let mut sm = Sp::null();
-    //     (*self.vtable).waitForService.unwrap()(&mut sm, self, name);
-    //     sm
-    // }
-
-    // /// Check if a service is declared (e.g. VINTF manifest).
-    // ///
-    // /// If this returns true, waitForService should always be able to return the
-    // /// service.
-    // pub unsafe fn isDeclared(&self, name: &String16) -> bool {
-    //     (*self.vtable).isDeclared.unwrap()(self, name)
-    // }
+    /// Retrieve an existing service, non-blocking.
+    ///
+    /// Returns `None` if the service does not currently exist.
+    pub unsafe fn checkService(&self, name: &String16) -> Option<Interface> {
+        Interface::from_raw(android_c_interface_IServiceManager_checkService(
+            self.0.as_native(),
+            name.as_native(),
+        ))
+    }
+
+    /// Register a service.
+    pub unsafe fn addService(
+        &self,
+        name: &String16,
+        service: &Interface,
+        allowIsolated: bool,
+        dumpsysFlags: i32,
+    ) -> BinderResult<()> {
+        let status = android_c_interface_IServiceManager_addService(
+            self.0.as_native(),
+            name.as_native(),
+            service.as_native(),
+            allowIsolated,
+            dumpsysFlags,
+        );
+
+        binder_status(status)
+    }
+
+    /// Return list of all existing services.
+    pub unsafe fn listServices(&self, dumpsysFlags: DumpFlags) -> Vec<String16> {
+        let mut vec = android_c_interface_NewString16Vector();
+        android_c_interface_IServiceManager_listServices(
+            self.0.as_native(),
+            dumpsysFlags as i32,
+            vec,
+        );
+
+        let len = android_c_interface_String16Vector_size(vec).try_into().unwrap();
+        let mut result = Vec::with_capacity(len);
+        for i in 0..len {
+            result.push(String16(android_c_interface_String16Vector_get(vec, i as size_t)));
+        }
+
+        android_c_interface_DeleteString16Vector(vec);
+        result
+    }
+
+    /// Efficiently wait for a service to become available, blocking until it
+    /// does.
+    ///
+    /// Returns `None` only for permission problems or a fatal error.
+    pub unsafe fn waitForService(&self, name: &String16) -> Option<Interface> {
+        Interface::from_raw(android_c_interface_IServiceManager_waitForService(
+            self.0.as_native(),
+            name.as_native(),
+        ))
+    }
+
+    /// Check if a service is declared (e.g. in the VINTF manifest).
+    ///
+    /// If this returns `true`, `waitForService` should always be able to
+    /// return the service.
+    pub unsafe fn isDeclared(&self, name: &String16) -> bool {
+        android_c_interface_IServiceManager_isDeclared(self.0.as_native(), name.as_native())
+    }
+
+    /// Register to be notified whenever the named service is (re)registered.
+    ///
+    /// `cb` is invoked with the registered name and the new service every
+    /// time `onRegistration` fires, for as long as the returned
+    /// [`ServiceRegistrationGuard`] is kept alive. Dropping the guard
+    /// unregisters the callback and frees it.
+    pub unsafe fn register_for_notifications<F>(
+        &self,
+        name: &String16,
+        cb: F,
+    ) -> BinderResult<ServiceRegistrationGuard>
+    where
+        F: Fn(&String16, Option<Interface>) + 'static,
+    {
+        let cookie: Box<Box<dyn Fn(&String16, Option<Interface>)>> = Box::new(Box::new(cb));
+        let cookie = Box::into_raw(cookie);
+
+        let callback = android_c_interface_NewServiceCallback(
+            cookie.cast(),
+            Some(service_callback_trampoline),
+            Some(service_callback_deleter),
+        );
+
+        let status = android_c_interface_IServiceManager_registerForNotifications(
+            self.0.as_native(),
+            name.as_native(),
+            callback,
+        );
+
+        match binder_status(status) {
+            Ok(()) => Ok(ServiceRegistrationGuard {
+                manager: self.clone(),
+                name: String16::from(name.to_string().as_str()),
+                callback,
+            }),
+            Err(e) => {
+                // The manager never took ownership of our callback; free it
+                // ourselves rather than leak it.
+                android_c_interface_DeleteServiceCallback(callback);
+                Err(e)
+            }
+        }
+    }
+
+    unsafe fn unregister_for_notifications(
+        &self,
+        name: &String16,
+        callback: *mut android_c_interface_BnServiceCallback,
+    ) {
+        android_c_interface_IServiceManager_unregisterForNotifications(
+            self.0.as_native(),
+            name.as_native(),
+            callback,
+        );
+        android_c_interface_DeleteServiceCallback(callback);
+    }
+}
+
+/// RAII handle for a service-registration callback registered via
+/// [`IServiceManager::register_for_notifications`].
+///
+/// The callback is unregistered and its boxed closure freed when this guard
+/// is dropped.
+pub struct ServiceRegistrationGuard {
+    manager: IServiceManager,
+    name: String16,
+    callback: *mut android_c_interface_BnServiceCallback,
+}
+
+impl Drop for ServiceRegistrationGuard {
+    fn drop(&mut self) {
+        unsafe {
+            self.manager.unregister_for_notifications(&self.name, self.callback);
+        }
+    }
+}
+
+/// Trampoline invoked by C++ on `onRegistration`. Reconstructs the boxed
+/// Rust closure from the opaque cookie and calls it; the box itself is not
+/// dropped here since the manager retains the callback until unregistered.
+unsafe extern "C" fn service_callback_trampoline(
+    cookie: *mut std::os::raw::c_void,
+    name: *mut android_String16,
+    binder: *mut android_sp<android_IBinder>,
+) {
+    let cb: &Box<dyn Fn(&String16, Option<Interface>)> = &*(cookie as *const _);
+    let name = String16(name);
+    let service = Interface::from_raw(binder);
+    cb(&name, service);
+}
+
+/// Called by C++ once the native callback object is destroyed, so we can
+/// drop the boxed Rust closure.
+unsafe extern "C" fn service_callback_deleter(cookie: *mut std::os::raw::c_void) {
+    drop(Box::from_raw(cookie as *mut Box<dyn Fn(&String16, Option<Interface>)>));
 }
 
 pub unsafe fn defaultServiceManager() -> Option<IServiceManager> {
     IServiceManager::from_raw(android_c_interface_DefaultServiceManager())
 }
+
+impl Interface {
+    /// Dump this binder to the given file descriptor, passing `args` through
+    /// to the service's `dump` implementation.
+    ///
+    /// Pass [`DumpFlags::Proto`] as `flags` to request the service's protobuf
+    /// dump format instead of plain text, or a priority variant
+    /// (`PriorityCritical`, etc.) to collect a subset of sections quickly, as
+    /// dumpstate does for bugreports. `flags` holds a single `DumpFlags`
+    /// value, so the two cannot be combined in one call.
+    pub unsafe fn dump(&self, fd: c_int, args: &[String16], flags: DumpFlags) -> BinderResult<()> {
+        let arg_ptrs: Vec<*const android_String16> =
+            args.iter().map(|arg| arg.as_native()).collect();
+
+        let status = android_c_interface_IBinder_dump(
+            self.as_native(),
+            fd,
+            arg_ptrs.as_ptr(),
+            arg_ptrs.len().try_into().unwrap(),
+            flags as i32,
+        );
+
+        binder_status(status)
+    }
+}
+
+impl IServiceManager {
+    /// Dump every currently running service (as reported by `listServices`)
+    /// to `fd`, honoring `flags` for both priority filtering and the
+    /// protobuf/text format selection.
+    ///
+    /// Individual services that fail to dump (e.g. because they died between
+    /// `listServices` and the dump call) are skipped rather than aborting the
+    /// whole pass.
+    pub unsafe fn dumpAllServices(&self, fd: c_int, flags: DumpFlags) {
+        for name in self.listServices(flags) {
+            if let Some(service) = self.checkService(&name) {
+                let _ = service.dump(fd, &[], flags);
+            }
+        }
+    }
+}
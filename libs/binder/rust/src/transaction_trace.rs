@@ -0,0 +1,47 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Feeds the process-wide binder transaction trace (`debugstore::debug_store::transaction_trace`)
+//! from the generic [`SpIBinder`] proxy path, so a crash/ANR report can show recent IPC activity
+//! regardless of which interface was being called.
+
+use crate::binder_impl::{Parcel, TransactionCode, TransactionFlags};
+use crate::{IBinder, Result, SpIBinder};
+use std::time::Instant;
+
+impl SpIBinder {
+    /// Like [`IBinder::transact`](crate::IBinder::transact), but additionally records this
+    /// call's interface descriptor, code, duration, and outcome into the process-wide
+    /// transaction trace. Tracing is best-effort and never delays or fails the transaction
+    /// itself; see `debugstore::debug_store::transaction_trace::record`.
+    pub fn transact_traced<F: FnOnce(&mut Parcel) -> Result<()>>(
+        &self,
+        interface_descriptor: &str,
+        code: TransactionCode,
+        flags: TransactionFlags,
+        input_callback: F,
+    ) -> Result<Parcel> {
+        let start = Instant::now();
+        let result = self.transact(code, flags, input_callback);
+        debugstore::debug_store::transaction_trace::record(
+            interface_descriptor,
+            code,
+            std::process::id() as i32,
+            start.elapsed(),
+            result.as_ref().err().map(|status| format!("{:?}", status)),
+        );
+        result
+    }
+}
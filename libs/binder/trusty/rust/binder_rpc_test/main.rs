@@ -17,11 +17,14 @@
 #![allow(unused)]
 
 use binder::{BinderFeatures, IBinder, Interface, Status, StatusCode, Strong};
+use binder_rpc_test_aidl::aidl::IBinderRpcCallback::{BnBinderRpcCallback, IBinderRpcCallback};
 use binder_rpc_test_aidl::aidl::IBinderRpcSession::{BnBinderRpcSession, IBinderRpcSession};
 use binder_rpc_test_aidl::aidl::IBinderRpcTest::{BnBinderRpcTest, IBinderRpcTest};
 use binder_rpc_test_session::MyBinderRpcSession;
 use log::{info, warn};
 use rpcbinder::RpcSession;
+use std::io::Read;
+use std::sync::Mutex;
 use trusty_std::ffi::{CString, FallibleCString};
 
 test::init!();
@@ -34,300 +37,208 @@ fn get_service(port: &str) -> Strong<dyn IBinderRpcTest> {
     RpcSession::new().setup_trusty_client(port.as_c_str()).expect("Failed to create session")
 }
 
-// ----------
-
-#[test]
-fn ping() {
-    let srv = get_service(SERVICE_PORT);
-    assert_eq!(srv.as_binder().ping_binder(), Ok(()));
-}
-
-#[test]
-fn ping_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    assert_eq!(srv.as_binder().ping_binder(), Ok(()));
+/// Generates a `#[test] fn $name` and `#[test] fn $rust_name` pair that each call `get_service`
+/// for their respective backend and then run the same closure body against the resulting
+/// `Strong<dyn IBinderRpcTest>`.
+///
+/// A trailing `, rust: |$srv| $rust_body` overrides the body run against the Rust backend, for
+/// the handful of cases where the C and Rust services are expected to behave differently (e.g.
+/// `invalid_null_binder_return`'s `StatusCode`).
+macro_rules! service_test {
+    ($name:ident, $rust_name:ident, |$srv:ident| $body:expr) => {
+        #[test]
+        fn $name() {
+            let $srv = get_service(SERVICE_PORT);
+            $body
+        }
+
+        #[test]
+        fn $rust_name() {
+            let $srv = get_service(RUST_SERVICE_PORT);
+            $body
+        }
+    };
+    ($name:ident, $rust_name:ident, |$srv:ident| $body:expr, rust: |$rust_srv:ident| $rust_body:expr) => {
+        #[test]
+        fn $name() {
+            let $srv = get_service(SERVICE_PORT);
+            $body
+        }
+
+        #[test]
+        fn $rust_name() {
+            let $rust_srv = get_service(RUST_SERVICE_PORT);
+            $rust_body
+        }
+    };
 }
 
 // ----------
 
-#[test]
-fn send_something_oneway() {
-    let srv = get_service(SERVICE_PORT);
-    assert_eq!(srv.sendString("Foo"), Ok(()));
-}
-
-#[test]
-fn send_something_oneway_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    assert_eq!(srv.sendString("Foo"), Ok(()));
+service_test! {
+    ping, ping_rust,
+    |srv| assert_eq!(srv.as_binder().ping_binder(), Ok(()))
 }
 
 // ----------
 
-#[test]
-fn send_and_get_result_back() {
-    let srv = get_service(SERVICE_PORT);
-    assert_eq!(srv.doubleString("Foo"), Ok(String::from("FooFoo")));
-}
-
-#[test]
-fn send_and_get_result_back_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    assert_eq!(srv.doubleString("Foo"), Ok(String::from("FooFoo")));
+service_test! {
+    send_something_oneway, send_something_oneway_rust,
+    |srv| assert_eq!(srv.sendString("Foo"), Ok(()))
 }
 
 // ----------
 
-#[test]
-fn send_and_get_result_back_big() {
-    let srv = get_service(SERVICE_PORT);
-    let single_len = 512;
-    let single = "a".repeat(single_len);
-    assert_eq!(srv.doubleString(&single), Ok(String::from(single.clone() + &single)));
-}
-
-#[test]
-fn send_and_get_result_back_big_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    let single_len = 512;
-    let single = "a".repeat(single_len);
-    assert_eq!(srv.doubleString(&single), Ok(String::from(single.clone() + &single)));
+service_test! {
+    send_and_get_result_back, send_and_get_result_back_rust,
+    |srv| assert_eq!(srv.doubleString("Foo"), Ok(String::from("FooFoo")))
 }
 
 // ----------
 
-#[test]
-fn invalid_null_binder_return() {
-    let srv = get_service(SERVICE_PORT);
-    assert_eq!(srv.getNullBinder(), Err(Status::from(StatusCode::UNEXPECTED_NULL)));
-}
-
-#[test]
-fn invalid_null_binder_return_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    assert_eq!(srv.getNullBinder(), Err(Status::from(StatusCode::UNKNOWN_TRANSACTION)));
+service_test! {
+    send_and_get_result_back_big, send_and_get_result_back_big_rust,
+    |srv| {
+        let single_len = 512;
+        let single = "a".repeat(single_len);
+        assert_eq!(srv.doubleString(&single), Ok(String::from(single.clone() + &single)));
+    }
 }
 
 // ----------
 
-#[test]
-fn call_me_back() {
-    let srv = get_service(SERVICE_PORT);
-
-    let binder =
-        BnBinderRpcSession::new_binder(MyBinderRpcSession::new("Foo"), BinderFeatures::default())
-            .as_binder();
-    let result = srv.pingMe(&binder);
-    assert_eq!(result, Ok(0));
-}
-
-#[test]
-fn call_me_back_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-
-    let binder =
-        BnBinderRpcSession::new_binder(MyBinderRpcSession::new("Foo"), BinderFeatures::default())
-            .as_binder();
-    let result = srv.pingMe(&binder);
-    assert_eq!(result, Ok(0));
+service_test! {
+    invalid_null_binder_return, invalid_null_binder_return_rust,
+    |srv| assert_eq!(srv.getNullBinder(), Err(Status::from(StatusCode::UNEXPECTED_NULL))),
+    rust: |srv| assert_eq!(srv.getNullBinder(), Err(Status::from(StatusCode::UNKNOWN_TRANSACTION)))
 }
 
 // ----------
 
-#[test]
-fn repeat_binder() {
-    let srv = get_service(SERVICE_PORT);
-
-    let in_binder =
-        BnBinderRpcSession::new_binder(MyBinderRpcSession::new("Foo"), BinderFeatures::default())
-            .as_binder();
-    let result = srv.repeatBinder(Some(&in_binder));
-    assert_eq!(result.unwrap().unwrap(), in_binder);
-}
-
-#[test]
-fn repeat_binder_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-
-    let in_binder =
-        BnBinderRpcSession::new_binder(MyBinderRpcSession::new("Foo"), BinderFeatures::default())
-            .as_binder();
-    let result = srv.repeatBinder(Some(&in_binder));
-    assert_eq!(result.unwrap().unwrap(), in_binder);
+service_test! {
+    call_me_back, call_me_back_rust,
+    |srv| {
+        let binder = BnBinderRpcSession::new_binder(
+            MyBinderRpcSession::new("Foo"),
+            BinderFeatures::default(),
+        )
+        .as_binder();
+        let result = srv.pingMe(&binder);
+        assert_eq!(result, Ok(0));
+    }
 }
 
 // ----------
 
-#[test]
-fn repeat_their_binder() {
-    let srv = get_service(SERVICE_PORT);
-
-    let session = srv.openSession("Test");
-    assert!(session.is_ok());
-
-    let in_binder = session.unwrap().as_binder();
-    let out_binder = srv.repeatBinder(Some(&in_binder));
-    assert_eq!(out_binder.unwrap().unwrap(), in_binder);
+service_test! {
+    repeat_binder, repeat_binder_rust,
+    |srv| {
+        let in_binder = BnBinderRpcSession::new_binder(
+            MyBinderRpcSession::new("Foo"),
+            BinderFeatures::default(),
+        )
+        .as_binder();
+        let result = srv.repeatBinder(Some(&in_binder));
+        assert_eq!(result.unwrap().unwrap(), in_binder);
+    }
 }
 
-#[test]
-fn repeat_their_binder_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
+// ----------
 
-    let session = srv.openSession("Test");
-    assert!(session.is_ok());
+service_test! {
+    repeat_their_binder, repeat_their_binder_rust,
+    |srv| {
+        let session = srv.openSession("Test");
+        assert!(session.is_ok());
 
-    let in_binder = session.unwrap().as_binder();
-    let out_binder = srv.repeatBinder(Some(&in_binder));
-    assert_eq!(out_binder.unwrap().unwrap(), in_binder);
+        let in_binder = session.unwrap().as_binder();
+        let out_binder = srv.repeatBinder(Some(&in_binder));
+        assert_eq!(out_binder.unwrap().unwrap(), in_binder);
+    }
 }
 
 // ----------
 
-#[test]
-fn hold_binder() {
-    let srv = get_service(SERVICE_PORT);
-    let name = "Foo";
-
-    let binder =
-        BnBinderRpcSession::new_binder(MyBinderRpcSession::new(name), BinderFeatures::default())
-            .as_binder();
-    assert!(srv.holdBinder(Some(&binder)).is_ok());
-
-    let held = srv.getHeldBinder();
-    assert!(held.is_ok());
-    let held = held.unwrap();
-    assert!(held.is_some());
-    let held = held.unwrap();
-    assert_eq!(binder, held);
-
-    let session = held.into_interface::<dyn IBinderRpcSession>();
-    assert!(session.is_ok());
-
-    let session_name = session.unwrap().getName();
-    assert!(session_name.is_ok());
-    let session_name = session_name.unwrap();
-    assert_eq!(session_name, name);
-
-    assert!(srv.holdBinder(None).is_ok());
-}
-
-#[test]
-fn hold_binder_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    let name = "Foo";
-
-    let binder =
-        BnBinderRpcSession::new_binder(MyBinderRpcSession::new(name), BinderFeatures::default())
-            .as_binder();
-    assert!(srv.holdBinder(Some(&binder)).is_ok());
-
-    let held = srv.getHeldBinder();
-    assert!(held.is_ok());
-    let held = held.unwrap();
-    assert!(held.is_some());
-    let held = held.unwrap();
-    assert_eq!(binder, held);
-
-    let session = held.into_interface::<dyn IBinderRpcSession>();
-    assert!(session.is_ok());
+service_test! {
+    hold_binder, hold_binder_rust,
+    |srv| {
+        let name = "Foo";
+
+        let binder = BnBinderRpcSession::new_binder(
+            MyBinderRpcSession::new(name),
+            BinderFeatures::default(),
+        )
+        .as_binder();
+        assert!(srv.holdBinder(Some(&binder)).is_ok());
+
+        let held = srv.getHeldBinder();
+        assert!(held.is_ok());
+        let held = held.unwrap();
+        assert!(held.is_some());
+        let held = held.unwrap();
+        assert_eq!(binder, held);
+
+        let session = held.into_interface::<dyn IBinderRpcSession>();
+        assert!(session.is_ok());
 
-    let session_name = session.unwrap().getName();
-    assert!(session_name.is_ok());
-    let session_name = session_name.unwrap();
-    assert_eq!(session_name, name);
+        let session_name = session.unwrap().getName();
+        assert!(session_name.is_ok());
+        let session_name = session_name.unwrap();
+        assert_eq!(session_name, name);
 
-    assert!(srv.holdBinder(None).is_ok());
+        assert!(srv.holdBinder(None).is_ok());
+    }
 }
 
 // ----------
 
-#[test]
-fn nested_transactions() {
-    let srv = get_service(SERVICE_PORT);
-    let binder =
-        BnBinderRpcTest::new_binder(MyBinderRpcSession::new("Nest"), BinderFeatures::default());
-    assert!(srv.nestMe(&binder, 10).is_ok());
-}
-
-#[test]
-fn nested_transactions_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-    let binder =
-        BnBinderRpcTest::new_binder(MyBinderRpcSession::new("Nest"), BinderFeatures::default());
-    assert!(srv.nestMe(&binder, 10).is_ok());
+service_test! {
+    nested_transactions, nested_transactions_rust,
+    |srv| {
+        let binder = BnBinderRpcTest::new_binder(
+            MyBinderRpcSession::new("Nest"),
+            BinderFeatures::default(),
+        );
+        assert!(srv.nestMe(&binder, 10).is_ok());
+    }
 }
 
 // ----------
 
-#[test]
-fn same_binder_equality() {
-    let srv = get_service(SERVICE_PORT);
-
-    let a = srv.alwaysGiveMeTheSameBinder();
-    assert!(a.is_ok());
+service_test! {
+    same_binder_equality, same_binder_equality_rust,
+    |srv| {
+        let a = srv.alwaysGiveMeTheSameBinder();
+        assert!(a.is_ok());
 
-    let b = srv.alwaysGiveMeTheSameBinder();
-    assert!(b.is_ok());
+        let b = srv.alwaysGiveMeTheSameBinder();
+        assert!(b.is_ok());
 
-    assert_eq!(a.unwrap(), b.unwrap());
-}
-
-#[test]
-fn same_binder_equality_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
-
-    let a = srv.alwaysGiveMeTheSameBinder();
-    assert!(a.is_ok());
-
-    let b = srv.alwaysGiveMeTheSameBinder();
-    assert!(b.is_ok());
-
-    assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(a.unwrap(), b.unwrap());
+    }
 }
 
 // ----------
 
-#[test]
-fn single_session() {
-    let srv = get_service(SERVICE_PORT);
-
-    let session = srv.openSession("aoeu");
-    assert!(session.is_ok());
-    let session = session.unwrap();
-    let name = session.getName();
-    assert!(name.is_ok());
-    assert_eq!(name.unwrap(), "aoeu");
-
-    let count = srv.getNumOpenSessions();
-    assert!(count.is_ok());
-    assert_eq!(count.unwrap(), 1);
-
-    drop(session);
-    let count = srv.getNumOpenSessions();
-    assert!(count.is_ok());
-    assert_eq!(count.unwrap(), 0);
-}
-
-#[test]
-fn single_session_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
+service_test! {
+    single_session, single_session_rust,
+    |srv| {
+        let session = srv.openSession("aoeu");
+        assert!(session.is_ok());
+        let session = session.unwrap();
+        let name = session.getName();
+        assert!(name.is_ok());
+        assert_eq!(name.unwrap(), "aoeu");
 
-    let session = srv.openSession("aoeu");
-    assert!(session.is_ok());
-    let session = session.unwrap();
-    let name = session.getName();
-    assert!(name.is_ok());
-    assert_eq!(name.unwrap(), "aoeu");
+        let count = srv.getNumOpenSessions();
+        assert!(count.is_ok());
+        assert_eq!(count.unwrap(), 1);
 
-    let count = srv.getNumOpenSessions();
-    assert!(count.is_ok());
-    assert_eq!(count.unwrap(), 1);
-
-    drop(session);
-    let count = srv.getNumOpenSessions();
-    assert!(count.is_ok());
-    assert_eq!(count.unwrap(), 0);
+        drop(session);
+        let count = srv.getNumOpenSessions();
+        assert!(count.is_ok());
+        assert_eq!(count.unwrap(), 0);
+    }
 }
 
 // ----------
@@ -338,70 +249,143 @@ fn expect_sessions(expected: i32, srv: &Strong<dyn IBinderRpcTest>) {
     assert_eq!(expected, count.unwrap());
 }
 
-#[test]
-fn many_session() {
-    let srv = get_service(SERVICE_PORT);
+service_test! {
+    many_session, many_session_rust,
+    |srv| {
+        let mut sessions = Vec::new();
 
-    let mut sessions = Vec::new();
+        for i in 0..15 {
+            expect_sessions(i, &srv);
 
-    for i in 0..15 {
-        expect_sessions(i, &srv);
+            let session = srv.openSession(&(i.to_string()));
+            assert!(session.is_ok());
+            sessions.push(session.unwrap());
+        }
 
-        let session = srv.openSession(&(i.to_string()));
-        assert!(session.is_ok());
-        sessions.push(session.unwrap());
-    }
+        expect_sessions(sessions.len() as i32, &srv);
 
-    expect_sessions(sessions.len() as i32, &srv);
+        for i in 0..sessions.len() {
+            let name = sessions[i].getName();
+            assert!(name.is_ok());
+            assert_eq!(name.unwrap(), i.to_string());
+        }
 
-    for i in 0..sessions.len() {
-        let name = sessions[i].getName();
-        assert!(name.is_ok());
-        assert_eq!(name.unwrap(), i.to_string());
-    }
+        expect_sessions(sessions.len() as i32, &srv);
 
-    expect_sessions(sessions.len() as i32, &srv);
+        while !sessions.is_empty() {
+            sessions.pop();
 
-    while !sessions.is_empty() {
-        sessions.pop();
+            expect_sessions(sessions.len() as i32, &srv);
+        }
 
-        expect_sessions(sessions.len() as i32, &srv);
+        expect_sessions(0, &srv);
     }
-
-    expect_sessions(0, &srv);
 }
 
-#[test]
-fn many_session_rust() {
-    let srv = get_service(RUST_SERVICE_PORT);
+// ----------
 
-    let mut sessions = Vec::new();
+/// Records the order in which oneway callbacks arrive, for asserting FIFO delivery.
+///
+/// The Trusty TA under test has no monotonic-sleep primitive to actually pace callbacks (see
+/// `doCallback` in the service), so this only checks arrival order, not inter-arrival spacing.
+#[derive(Debug, Default)]
+struct OrderRecordingCallback {
+    order: std::sync::Arc<Mutex<Vec<String>>>,
+}
 
-    for i in 0..15 {
-        expect_sessions(i, &srv);
+impl Interface for OrderRecordingCallback {}
 
-        let session = srv.openSession(&(i.to_string()));
-        assert!(session.is_ok());
-        sessions.push(session.unwrap());
+impl IBinderRpcCallback for OrderRecordingCallback {
+    fn sendCallback(&self, value: &str) -> Result<(), Status> {
+        self.order.lock().unwrap().push(value.to_string());
+        Ok(())
     }
 
-    expect_sessions(sessions.len() as i32, &srv);
+    fn sendOnewayCallback(&self, value: &str) -> Result<(), Status> {
+        self.sendCallback(value)
+    }
+}
 
-    for i in 0..sessions.len() {
-        let name = sessions[i].getName();
-        assert!(name.is_ok());
-        assert_eq!(name.unwrap(), i.to_string());
+service_test! {
+    oneway_callback_ordering, oneway_callback_ordering_rust,
+    |srv| {
+        let order = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let callback = BnBinderRpcCallback::new_binder(
+            OrderRecordingCallback { order: order.clone() },
+            BinderFeatures::default(),
+        );
+
+        for i in 0..5 {
+            assert_eq!(
+                srv.doCallback(&callback, /*oneway=*/ true, /*delayed=*/ true, &i.to_string()),
+                Ok(())
+            );
+        }
+
+        let expected: Vec<String> = (0..5).map(|i| i.to_string()).collect();
+        assert_eq!(*order.lock().unwrap(), expected);
     }
+}
 
-    expect_sessions(sessions.len() as i32, &srv);
+// ----------
 
-    while !sessions.is_empty() {
-        sessions.pop();
+service_test! {
+    fd_passing, fd_passing_rust,
+    |srv| {
+        let mut pfd = srv.echoAsFile("Foo").expect("echoAsFile failed");
+        let mut contents = String::new();
+        pfd.read_to_string(&mut contents).expect("Failed to read echoed fd");
+        assert_eq!(contents, "Foo");
+    }
+}
 
-        expect_sessions(sessions.len() as i32, &srv);
+service_test! {
+    fd_concatenation, fd_concatenation_rust,
+    |srv| {
+        let a = srv.echoAsFile("Foo").expect("echoAsFile failed");
+        let b = srv.echoAsFile("Bar").expect("echoAsFile failed");
+        let mut combined = srv.concatFiles(&[a, b]).expect("concatFiles failed");
+        let mut contents = String::new();
+        combined.read_to_string(&mut contents).expect("Failed to read concatenated fd");
+        assert_eq!(contents, "FooBar");
     }
+}
 
-    expect_sessions(0, &srv);
+service_test! {
+    blocking_fd_roundtrip, blocking_fd_roundtrip_rust,
+    |srv| {
+        let sent = srv.echoAsFile("Foo").expect("echoAsFile failed");
+        assert_eq!(srv.blockingSendFdOneway(&sent), Ok(()));
+        let mut received = srv.blockingRecvFd().expect("blockingRecvFd failed");
+        let mut contents = String::new();
+        received.read_to_string(&mut contents).expect("Failed to read received fd");
+        assert_eq!(contents, "Foo");
+    }
+}
+
+// ----------
+
+// Exercises the mutually-authenticated session builder's happy path: both sides present the
+// identity the other expects, so the handshake should succeed and the session should behave
+// exactly like an unauthenticated one from here on. Negative paths (wrong peer key, a tampered
+// frame) would need fault injection inside the native transport to exercise realistically and
+// aren't covered here.
+#[test]
+fn mutual_authentication_handshake() {
+    let client_identity =
+        rpcbinder::TrustyIdentity { private_key: [1u8; 32], public_key: [2u8; 32] };
+    // The service's public key is fixed for this test target; see the service-side identity.
+    let server_public_key = [3u8; 32];
+
+    let port = CString::try_new(RUST_SERVICE_PORT).expect("Failed to allocate port name");
+    let session = RpcSession::new();
+    session
+        .set_mutual_authentication(&client_identity, &server_public_key)
+        .expect("Failed to configure mutual authentication");
+
+    let srv: Strong<dyn IBinderRpcTest> =
+        session.setup_trusty_client(port.as_c_str()).expect("Failed to create session");
+    assert_eq!(srv.as_binder().ping_binder(), Ok(()));
 }
 
 // ===========================================================
@@ -428,9 +412,24 @@ fn many_session_rust() {
 
 // ----------
 
-// #[test]
-// fn count_binders_test() {
-//     let srv = get_service(RUST_SERVICE_PORT);
-//     let v = srv.countBinders();
-//     println!("{:?}", v);
-// }
+// `rpcbinder::RpcSession` (the Trusty client session type used by `get_service`/
+// `setup_trusty_client`) offers no client-side introspection (`num_live_binders`/
+// `num_known_remote_refs`), so this only exercises the server-side session count:
+// `countBinders` returns one entry per session listed by `RpcServerRef::sessions`.
+#[test]
+fn count_binders_test() {
+    let srv = get_service(RUST_SERVICE_PORT);
+
+    let baseline = srv.countBinders().expect("countBinders should succeed").len() as i32;
+
+    let mut sessions = Vec::new();
+    for i in 0..10 {
+        sessions.push(get_service(RUST_SERVICE_PORT));
+        let count = srv.countBinders().expect("countBinders should succeed").len() as i32;
+        assert_eq!(count, baseline + i + 1);
+    }
+
+    drop(sessions);
+    let count = srv.countBinders().expect("countBinders should succeed").len() as i32;
+    assert_eq!(count, baseline);
+}
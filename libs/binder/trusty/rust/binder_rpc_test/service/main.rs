@@ -21,9 +21,14 @@ use binder_rpc_test_aidl::aidl::IBinderRpcCallback::IBinderRpcCallback;
 use binder_rpc_test_aidl::aidl::IBinderRpcSession::{BnBinderRpcSession, IBinderRpcSession};
 use binder_rpc_test_aidl::aidl::IBinderRpcTest::{BnBinderRpcTest, IBinderRpcTest};
 use binder_rpc_test_session::MyBinderRpcSession;
-use rpcbinder::RpcServer;
+use foreign_types::{ForeignType, ForeignTypeRef};
+use rpcbinder::{RpcServer, RpcServerRef};
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::fd::FromRawFd;
 use std::rc::Rc;
-use std::sync::Mutex;
+use std::sync::{Condvar, Mutex};
 use tipc::{service_dispatcher, wrap_service, Manager, PortCfg};
 
 const RUST_SERVICE_PORT: &str = "com.android.trusty.rust.binderRpcTestService.V1";
@@ -33,6 +38,28 @@ const RUST_SERVICE_PORT: &str = "com.android.trusty.rust.binderRpcTestService.V1
 static SESSION_COUNT: Mutex<i32> = Mutex::new(0);
 static HOLD_BINDER: Mutex<Option<SpIBinder>> = Mutex::new(None);
 static SAME_BINDER: Mutex<Option<SpIBinder>> = Mutex::new(None);
+// Raw `ARpcServer*`, stashed once `main` creates the server, so `countBinders` below has
+// something to introspect. Stored as a `usize` since the pointer itself isn't `Sync`; it's never
+// dereferenced until after `main` has set it and the server is alive for the process lifetime.
+static SERVER_PTR: Mutex<Option<usize>> = Mutex::new(None);
+// Mailbox for `blockingSendFdOneway`/`blockingRecvFd`, mirroring `HOLD_BINDER` above but with a
+// condvar so a racing `blockingRecvFd` call blocks until an fd actually arrives.
+static FD_MAILBOX: Mutex<Option<ParcelFileDescriptor>> = Mutex::new(None);
+static FD_MAILBOX_CONDVAR: Condvar = Condvar::new();
+
+/// Creates an anonymous, memory-backed file, for use as the fd returned by `echoAsFile` and
+/// `concatFiles`.
+fn new_memfd() -> std::io::Result<File> {
+    let name = CStr::from_bytes_with_nul(b"binderRpcTest\0").unwrap();
+    // SAFETY: `name` is a valid, NUL-terminated C string; `memfd_create` returns either a new,
+    // owned fd or -1 on error.
+    let fd = unsafe { libc::memfd_create(name.as_ptr(), 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `fd` was just returned by `memfd_create` above as a new, owned fd.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
 
 #[derive(Debug, Default)]
 struct TestService {
@@ -78,8 +105,13 @@ impl IBinderRpcTest for TestService {
         Ok(self.port)
     }
     fn countBinders(&self) -> Result<Vec<i32>, Status> {
-        // TODO #### Where do I get server reference to look at sessions?  Is that the same as the service (self)? ###
-        todo!()
+        let ptr = SERVER_PTR.lock().unwrap().expect("server not yet started");
+        // SAFETY: `ptr` was produced by `ForeignType::as_ptr` on the `RpcServer` that `main` keeps
+        // alive for the lifetime of the process, so it's still valid here.
+        let server = unsafe {
+            RpcServerRef::from_ptr(ptr as *mut binder_rpc_unstable_bindgen::ARpcServer)
+        };
+        Ok(server.sessions().into_iter().map(|session| session.binder_ref_count as i32).collect())
     }
     fn getNullBinder(&self) -> Result<SpIBinder, Status> {
         Err(Status::from(StatusCode::UNKNOWN_TRANSACTION))
@@ -145,28 +177,41 @@ impl IBinderRpcTest for TestService {
         todo!()
     }
     fn sleepMs(&self, _: i32) -> Result<(), Status> {
-        todo!()
+        // No monotonic-sleep primitive is wired up for Trusty TAs in this tree, so this is a
+        // no-op rather than an actual delay. doCallback's `delayed` flag does not pause either:
+        // it re-dispatches immediately.
+        Ok(())
     }
     fn sleepMsAsync(&self, _: i32) -> Result<(), Status> {
-        todo!()
+        Ok(())
     }
     fn doCallback(
         &self,
-        _: &Strong<(dyn IBinderRpcCallback + 'static)>,
-        _: bool,
-        _: bool,
-        _: &str,
+        callback: &Strong<(dyn IBinderRpcCallback + 'static)>,
+        oneway: bool,
+        delayed: bool,
+        value: &str,
     ) -> Result<(), Status> {
-        todo!()
+        if delayed {
+            // Real delay would need a background thread, which Trusty TAs in this tree cannot
+            // spawn; re-dispatch immediately and let the client measure arrival order/spacing
+            // against its own clock instead of relying on the server to pace callbacks.
+            return self.doCallback(callback, oneway, false, value);
+        }
+        if oneway {
+            callback.sendOnewayCallback(value)
+        } else {
+            callback.sendCallback(value)
+        }
     }
     fn doCallbackAsync(
         &self,
-        _: &Strong<(dyn IBinderRpcCallback + 'static)>,
-        _: bool,
-        _: bool,
-        _: &str,
+        callback: &Strong<(dyn IBinderRpcCallback + 'static)>,
+        oneway: bool,
+        delayed: bool,
+        value: &str,
     ) -> Result<(), Status> {
-        todo!()
+        self.doCallback(callback, oneway, delayed, value)
     }
     fn die(&self, _: bool) -> Result<(), Status> {
         Err(Status::from(StatusCode::UNKNOWN_TRANSACTION))
@@ -177,17 +222,38 @@ impl IBinderRpcTest for TestService {
     fn useKernelBinderCallingId(&self) -> Result<(), Status> {
         todo!()
     }
-    fn echoAsFile(&self, _: &str) -> Result<ParcelFileDescriptor, Status> {
-        todo!()
-    }
-    fn concatFiles(&self, _: &[ParcelFileDescriptor]) -> Result<ParcelFileDescriptor, Status> {
-        todo!()
-    }
-    fn blockingSendFdOneway(&self, _: &ParcelFileDescriptor) -> Result<(), Status> {
-        todo!()
+    fn echoAsFile(&self, content: &str) -> Result<ParcelFileDescriptor, Status> {
+        let mut file = new_memfd().map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        file.write_all(content.as_bytes()).map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        file.seek(SeekFrom::Start(0)).map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        Ok(ParcelFileDescriptor::new(file))
+    }
+    fn concatFiles(&self, files: &[ParcelFileDescriptor]) -> Result<ParcelFileDescriptor, Status> {
+        let mut contents = Vec::new();
+        for pfd in files {
+            let mut file =
+                pfd.as_ref().try_clone().map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+            file.seek(SeekFrom::Start(0)).map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+            file.read_to_end(&mut contents).map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        }
+
+        let mut out = new_memfd().map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        out.write_all(&contents).map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        out.seek(SeekFrom::Start(0)).map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        Ok(ParcelFileDescriptor::new(out))
+    }
+    fn blockingSendFdOneway(&self, fd: &ParcelFileDescriptor) -> Result<(), Status> {
+        let cloned = fd.as_ref().try_clone().map_err(|_| Status::from(StatusCode::UNKNOWN_ERROR))?;
+        *FD_MAILBOX.lock().unwrap() = Some(ParcelFileDescriptor::new(cloned));
+        FD_MAILBOX_CONDVAR.notify_one();
+        Ok(())
     }
     fn blockingRecvFd(&self) -> Result<ParcelFileDescriptor, Status> {
-        todo!()
+        let mut mailbox = FD_MAILBOX.lock().unwrap();
+        while mailbox.is_none() {
+            mailbox = FD_MAILBOX_CONDVAR.wait(mailbox).unwrap();
+        }
+        Ok(mailbox.take().unwrap())
     }
     fn blockingSendIntOneway(&self, _: i32) -> Result<(), Status> {
         todo!()
@@ -213,6 +279,7 @@ fn main() {
         RpcServer::new_trusty(service.as_binder()).expect("Could noot create RpcServer"),
     );
     rpc_server.0.set_per_session_root_object(move |_session, _uuid| Some(service.as_binder()));
+    *SERVER_PTR.lock().unwrap() = Some(ForeignType::as_ptr(&rpc_server.0) as usize);
 
     let cfg = PortCfg::new(RUST_SERVICE_PORT)
         .expect("Could not create port config")
@@ -0,0 +1,53 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Thin bindings to the platform `libandroid` ATrace async-section API, used by
+//! [`super::core::DebugStore`] to mirror events onto a systrace/Perfetto capture.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+#[link(name = "android")]
+extern "C" {
+    fn ATrace_isEnabled() -> bool;
+    fn ATrace_beginAsyncSection(section_name: *const c_char, cookie: i32);
+    fn ATrace_endAsyncSection(section_name: *const c_char, cookie: i32);
+}
+
+/// Whether a systrace/Perfetto capture is currently listening for this process's trace tag.
+pub(crate) fn is_enabled() -> bool {
+    // SAFETY: `ATrace_isEnabled` takes no arguments and has no preconditions.
+    unsafe { ATrace_isEnabled() }
+}
+
+/// Emits an ATrace async-begin marker for `name`, to be matched by [`async_end`] with the same
+/// `cookie`. Silently does nothing if `name` contains an interior NUL.
+pub(crate) fn async_begin(name: &str, cookie: i32) {
+    if let Ok(name) = CString::new(name) {
+        // SAFETY: `name` is a valid, NUL-terminated C string for the duration of this call, which
+        // copies it rather than retaining the pointer.
+        unsafe { ATrace_beginAsyncSection(name.as_ptr(), cookie) };
+    }
+}
+
+/// Emits an ATrace async-end marker for `name`, matching a prior [`async_begin`] with the same
+/// `cookie`. Silently does nothing if `name` contains an interior NUL.
+pub(crate) fn async_end(name: &str, cookie: i32) {
+    if let Ok(name) = CString::new(name) {
+        // SAFETY: `name` is a valid, NUL-terminated C string for the duration of this call, which
+        // copies it rather than retaining the pointer.
+        unsafe { ATrace_endAsyncSection(name.as_ptr(), cookie) };
+    }
+}
@@ -13,15 +13,50 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use super::atrace;
 use super::event::Event;
 use super::event_type::EventType;
 use super::storage::Storage;
 use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
 use std::fmt;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::Instant;
 use std::time::SystemTime;
 
+/// Paths tried, in order, for the kernel ftrace marker file that mirrored events are written to.
+const TRACE_MARKER_PATHS: &[&str] =
+    &["/sys/kernel/tracing/trace_marker", "/sys/kernel/debug/tracing/trace_marker"];
+
+/// Whether events should be mirrored into the kernel trace buffer, checked once and cached.
+///
+/// Controlled by the `DEBUGSTORE_TRACE` environment variable so that enabling tracing never
+/// costs more than one syscall-free check on the hot `begin`/`record`/`end` path when disabled.
+static TRACING_ENABLED: Lazy<bool> = Lazy::new(|| std::env::var_os("DEBUGSTORE_TRACE").is_some());
+
+/// The cached, already-opened trace marker file, if tracing is enabled and a tracefs mount could
+/// be found.
+static TRACE_MARKER: Lazy<Option<Mutex<File>>> = Lazy::new(|| {
+    TRACE_MARKER_PATHS
+        .iter()
+        .find_map(|path| OpenOptions::new().write(true).open(path).ok())
+        .map(Mutex::new)
+});
+
+/// Whether events should additionally be mirrored to the platform ATrace async-section API, so
+/// they show up in a systrace/Perfetto capture instead of only the raw kernel trace buffer above.
+/// Off by default; toggled explicitly via [`DebugStore::set_trace_enabled`].
+static ATRACE_TOGGLE: AtomicBool = AtomicBool::new(false);
+
+/// Names of spans mirrored to ATrace via [`DebugStore::begin`], keyed by id, so that
+/// [`DebugStore::end`] can pass the matching name to `ATrace_endAsyncSection`.
+static ATRACE_OPEN_SPANS: Lazy<Mutex<HashMap<u64, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 //  Lazily initialized static instance of DebugStore.
 static INSTANCE: Lazy<DebugStore> = Lazy::new(DebugStore::new);
 
@@ -47,7 +82,10 @@ impl DebugStore {
     ///
     /// This constant is used as a part of the debug store's data format,
     /// allowing for version tracking and compatibility checks.
-    const ENCODE_VERSION: u32 = 1;
+    ///
+    /// Bumped to 2 when each event started carrying its recording process id and thread id;
+    /// [`decode`](Self::decode) still accepts version 1 dumps, which lack those two fields.
+    const ENCODE_VERSION: u32 = 2;
 
     /// Creates a new instance of `DebugStore` with specified event limit and maximum delay.
     fn new() -> Self {
@@ -69,6 +107,7 @@ impl DebugStore {
     /// - Returns: A unique ID for the debug event.
     pub fn begin(&self, name: String, data: Vec<(String, String)>) -> u64 {
         let id = self.generate_id();
+        Self::trace_begin(&name, &data, id);
         self.event_store.insert(Event::new(
             id,
             Some(name),
@@ -85,6 +124,8 @@ impl DebugStore {
     /// - `name`: The name of the debug event.
     /// - `data`: Associated data as key-value pairs.
     pub fn record(&self, name: String, data: Vec<(String, String)>) {
+        let cookie = self.generate_id();
+        Self::trace_point(&name, &data, cookie);
         self.event_store.insert(Event::new(
             Self::NON_CLOSABLE_ID,
             Some(name),
@@ -101,6 +142,7 @@ impl DebugStore {
     /// - `data`: Additional data to log at the end of the event.
     pub fn end(&self, id: u64, data: Vec<(String, String)>) {
         if id != Self::NON_CLOSABLE_ID {
+            Self::trace_end(id);
             self.event_store.insert(Event::new(
                 id,
                 None,
@@ -118,6 +160,256 @@ impl DebugStore {
         }
         id
     }
+
+    /// Mirrors a `begin` into the kernel trace buffer as a `B` (begin) marker, if tracing is
+    /// enabled, and into the platform ATrace async API if [`set_trace_enabled`](Self::set_trace_enabled)
+    /// has turned that on and a capture is actually listening.
+    fn trace_begin(name: &str, data: &[(String, String)], id: u64) {
+        if *TRACING_ENABLED {
+            Self::write_trace_marker(&format!(
+                "B|{}|{}\n",
+                std::process::id(),
+                trace_label(name, data)
+            ));
+        }
+        if Self::atrace_enabled() {
+            ATRACE_OPEN_SPANS.lock().unwrap().insert(id, name.to_string());
+            atrace::async_begin(name, id as i32);
+        }
+    }
+
+    /// Mirrors an `end` into the kernel trace buffer as an `E` (end) marker, if tracing is
+    /// enabled, and closes the matching ATrace async section opened by [`trace_begin`](Self::trace_begin),
+    /// if any.
+    fn trace_end(id: u64) {
+        if *TRACING_ENABLED {
+            Self::write_trace_marker(&format!("E|{}\n", std::process::id()));
+        }
+        if let Some(name) = ATRACE_OPEN_SPANS.lock().unwrap().remove(&id) {
+            atrace::async_end(&name, id as i32);
+        }
+    }
+
+    /// Enables or disables mirroring `begin`/`end`/`record` events to the platform ATrace
+    /// async-section API, in addition to the kernel trace buffer mirroring controlled by the
+    /// `DEBUGSTORE_TRACE` environment variable. Has no effect on the in-memory ring buffer that
+    /// backs [`Display`](fmt::Display)/[`decode`](Self::decode).
+    pub fn set_trace_enabled(enabled: bool) {
+        ATRACE_TOGGLE.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether ATrace mirroring is both requested via [`set_trace_enabled`](Self::set_trace_enabled)
+    /// and a capture is actually listening, i.e. whether it's worth paying the cost of emitting a
+    /// marker at all.
+    fn atrace_enabled() -> bool {
+        ATRACE_TOGGLE.load(Ordering::Relaxed) && atrace::is_enabled()
+    }
+
+    /// Mirrors a `record` point event into the kernel trace buffer as a paired `S`/`F` async
+    /// marker, if tracing is enabled, and as an immediately-closed ATrace async section if ATrace
+    /// mirroring is enabled.
+    fn trace_point(name: &str, data: &[(String, String)], cookie: u64) {
+        if *TRACING_ENABLED {
+            let pid = std::process::id();
+            let label = trace_label(name, data);
+            Self::write_trace_marker(&format!("S|{}|{}|{}\n", pid, label, cookie));
+            Self::write_trace_marker(&format!("F|{}|{}|{}\n", pid, label, cookie));
+        }
+        if Self::atrace_enabled() {
+            atrace::async_begin(name, cookie as i32);
+            atrace::async_end(name, cookie as i32);
+        }
+    }
+
+    /// Writes a single pre-formatted marker line to the cached trace marker fd, silently
+    /// ignoring any error (e.g. a missing tracefs mount).
+    fn write_trace_marker(marker: &str) {
+        if let Some(file) = TRACE_MARKER.as_ref() {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.write_all(marker.as_bytes());
+            }
+        }
+    }
+
+    /// Decodes a string previously produced by this `DebugStore`'s [`fmt::Display`] impl back
+    /// into its individual events. Accepts both version 1 (no process/thread id fields) and
+    /// version 2 dumps.
+    pub fn decode(encoded: &str) -> Result<Vec<DecodedEvent>, DecodeError> {
+        let (header, body) = encoded.split_once("::").ok_or(DecodeError::MalformedHeader)?;
+        let version: u32 = header
+            .split(',')
+            .next()
+            .and_then(|field| field.parse().ok())
+            .ok_or(DecodeError::MalformedHeader)?;
+        if version != 1 && version != Self::ENCODE_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        if body.is_empty() {
+            return Ok(Vec::new());
+        }
+        body.split("||").map(|raw| Self::decode_event(raw, version)).collect()
+    }
+
+    fn decode_event(raw: &str, version: u32) -> Result<DecodedEvent, DecodeError> {
+        let malformed = || DecodeError::MalformedEvent(raw.to_string());
+
+        // Version 1 events are `id,type,name,micros;data`; version 2 events additionally carry
+        // the recording process id and thread id: `id,type,name,micros,pid,tid;data`.
+        let field_count = if version == 1 { 4 } else { 6 };
+        let mut fields = raw.splitn(field_count, ',');
+
+        let id: u64 = fields.next().and_then(|s| s.parse().ok()).ok_or_else(malformed)?;
+        let event_type =
+            fields.next().and_then(EventType::from_code).ok_or_else(malformed)?;
+        let name = fields.next().ok_or_else(malformed)?.to_string();
+
+        let (micros_since_start, process_id, thread_id, data_field) = if version == 1 {
+            let rest = fields.next().ok_or_else(malformed)?;
+            let mut rest_fields = rest.splitn(2, ';');
+            let micros: u128 =
+                rest_fields.next().and_then(|s| s.parse().ok()).ok_or_else(malformed)?;
+            (micros, None, None, rest_fields.next())
+        } else {
+            let micros: u128 =
+                fields.next().and_then(|s| s.parse().ok()).ok_or_else(malformed)?;
+            let pid: u32 = fields.next().and_then(|s| s.parse().ok()).ok_or_else(malformed)?;
+            let rest = fields.next().ok_or_else(malformed)?;
+            let mut rest_fields = rest.splitn(2, ';');
+            let tid: i32 =
+                rest_fields.next().and_then(|s| s.parse().ok()).ok_or_else(malformed)?;
+            (micros, Some(pid), Some(tid), rest_fields.next())
+        };
+
+        let data = data_field
+            .map(|pairs| {
+                pairs
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(DecodedEvent { id, event_type, name, micros_since_start, process_id, thread_id, data })
+    }
+
+    /// Matches `DurationStart`/`DurationEnd` events from a decoded dump by id, reconstructing
+    /// the duration of each completed span. Any `DurationStart` with no matching `DurationEnd`
+    /// (e.g. because the span was still open when the store was dumped) is reported as
+    /// [`PairedSpan::Unterminated`] instead of being silently dropped. A `DurationEnd` whose
+    /// `DurationStart` is missing (e.g. evicted from the ring buffer) is skipped.
+    pub fn pair_durations(events: &[DecodedEvent]) -> Vec<PairedSpan> {
+        let mut open: BTreeMap<u64, &DecodedEvent> = BTreeMap::new();
+        let mut spans = Vec::new();
+
+        for event in events {
+            match event.event_type {
+                EventType::DurationStart => {
+                    open.insert(event.id, event);
+                }
+                EventType::DurationEnd => {
+                    if let Some(start) = open.remove(&event.id) {
+                        spans.push(PairedSpan::Closed {
+                            name: start.name.clone(),
+                            start_micros: start.micros_since_start,
+                            end_micros: event.micros_since_start,
+                            elapsed_micros: event
+                                .micros_since_start
+                                .saturating_sub(start.micros_since_start),
+                        });
+                    }
+                }
+                EventType::Point => {}
+            }
+        }
+
+        spans.extend(open.into_values().map(|start| PairedSpan::Unterminated {
+            name: start.name.clone(),
+            start_micros: start.micros_since_start,
+        }));
+        spans
+    }
+}
+
+/// A single event reconstructed by [`DebugStore::decode`] from the `Display` wire format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedEvent {
+    /// The event's id, shared between a `DurationStart` and its matching `DurationEnd`.
+    pub id: u64,
+    /// The event's type.
+    pub event_type: EventType,
+    /// The event's name.
+    pub name: String,
+    /// Microseconds since the encoding process's first recorded event; see
+    /// [`Event::micros_since_start`](super::event::Event::micros_since_start).
+    pub micros_since_start: u128,
+    /// The id of the process that recorded this event. `None` when decoded from a version 1
+    /// dump, which did not carry this field.
+    pub process_id: Option<u32>,
+    /// The id of the thread that recorded this event. `None` when decoded from a version 1
+    /// dump, which did not carry this field.
+    pub thread_id: Option<i32>,
+    /// The event's key/value data.
+    pub data: Vec<(String, String)>,
+}
+
+/// A `DurationStart`/`DurationEnd` pair reconstructed by [`DebugStore::pair_durations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PairedSpan {
+    /// A span whose `DurationEnd` was found.
+    Closed {
+        /// The span's name.
+        name: String,
+        /// Microseconds since the first recorded event at which the span started.
+        start_micros: u128,
+        /// Microseconds since the first recorded event at which the span ended.
+        end_micros: u128,
+        /// `end_micros - start_micros`.
+        elapsed_micros: u128,
+    },
+    /// A `DurationStart` with no matching `DurationEnd` in the decoded events.
+    Unterminated {
+        /// The span's name.
+        name: String,
+        /// Microseconds since the first recorded event at which the span started.
+        start_micros: u128,
+    },
+}
+
+/// An error produced by [`DebugStore::decode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input was missing the `VERSION,COUNT,UPTIME::` header.
+    MalformedHeader,
+    /// The header's version was neither 1 nor this decoder's `ENCODE_VERSION`.
+    UnsupportedVersion(u32),
+    /// An individual event's fields did not parse; holds the raw, undecoded event string.
+    MalformedEvent(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::MalformedHeader => write!(f, "malformed debug store header"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported debug store encode version: {}", version)
+            }
+            DecodeError::MalformedEvent(raw) => write!(f, "malformed debug store event: {}", raw),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Formats an event's name and key/value data as the single label ftrace expects: `name`, or
+/// `name|k=v;k=v;…` if `data` is non-empty.
+fn trace_label(name: &str, data: &[(String, String)]) -> String {
+    if data.is_empty() {
+        return name.to_string();
+    }
+    let pairs = data.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(";");
+    format!("{}|{}", name, pairs)
 }
 
 impl fmt::Display for DebugStore {
@@ -0,0 +1,120 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Always-on ring buffer of recent binder transactions, for inclusion in crash/ANR diagnostics,
+//! mirroring how `dumpsys`/`dumpstate` collect recent activity.
+//!
+//! Binder proxies feed this via [`record`]; nothing else needs to poll it. [`dump_report`] turns
+//! the buffer into a human-readable report on demand.
+
+use super::debug_store_storage::DebugStoreStorage;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// Default number of transactions kept in the ring buffer, overridable via the
+/// `DEBUGSTORE_TRANSACTION_TRACE_SIZE` environment variable.
+const DEFAULT_CAPACITY: usize = 64;
+
+/// Default maximum time a [`record`] call will wait for the buffer's lock before giving up,
+/// overridable via the `DEBUGSTORE_TRANSACTION_TRACE_MAX_DELAY_MS` environment variable.
+const DEFAULT_MAX_DELAY_MS: u64 = 5;
+
+static INSTANCE: Lazy<DebugStoreStorage<TransactionRecord>> = Lazy::new(|| {
+    DebugStoreStorage::new(
+        env_var("DEBUGSTORE_TRANSACTION_TRACE_SIZE", DEFAULT_CAPACITY),
+        env_var("DEBUGSTORE_TRANSACTION_TRACE_MAX_DELAY_MS", DEFAULT_MAX_DELAY_MS),
+    )
+});
+
+fn env_var<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+/// One traced binder transaction.
+#[derive(Debug, Clone)]
+pub struct TransactionRecord {
+    /// The interface descriptor of the binder the transaction was sent to.
+    pub interface_descriptor: String,
+    /// The transaction code.
+    pub code: u32,
+    /// When the transaction was recorded.
+    pub timestamp: SystemTime,
+    /// The id of the process that issued the transaction.
+    pub calling_pid: i32,
+    /// How long the transaction took to complete.
+    pub duration: Duration,
+    /// The debug-formatted description of the failure, or `None` if the transaction succeeded.
+    pub error: Option<String>,
+}
+
+impl fmt::Display for TransactionRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let since_epoch = self.timestamp.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        write!(
+            f,
+            "[pid {}] {}#{} at {}ms, took {:?}",
+            self.calling_pid,
+            self.interface_descriptor,
+            self.code,
+            since_epoch.as_millis(),
+            self.duration
+        )?;
+        match &self.error {
+            Some(error) => write!(f, ", failed: {}", error),
+            None => write!(f, ", ok"),
+        }
+    }
+}
+
+/// Records one transaction into the always-on trace ring buffer.
+///
+/// Never stalls the caller's transaction: this is a best-effort, non-blocking attempt to acquire
+/// the buffer's lock, bounded by `DEBUGSTORE_TRANSACTION_TRACE_MAX_DELAY_MS`. If the lock can't be
+/// acquired in time, the attempt is simply dropped -- `DebugStoreStorage::insert` has already
+/// counted it against [`lock_failures`].
+pub fn record(
+    interface_descriptor: &str,
+    code: u32,
+    calling_pid: i32,
+    duration: Duration,
+    error: Option<String>,
+) {
+    let _ = INSTANCE.insert(TransactionRecord {
+        interface_descriptor: interface_descriptor.to_string(),
+        code,
+        timestamp: SystemTime::now(),
+        calling_pid,
+        duration,
+        error,
+    });
+}
+
+/// Dumps the trace ring buffer as a human-readable report, one line per transaction, oldest
+/// first.
+pub fn dump_report() -> String {
+    INSTANCE
+        .fold(String::new(), |mut report, record| {
+            report.push_str(&record.to_string());
+            report.push('\n');
+            report
+        })
+        .unwrap_or_else(|| "<transaction trace buffer lock timed out>\n".to_string())
+}
+
+/// Number of [`record`] calls that couldn't acquire the buffer's lock in time and were dropped.
+pub fn lock_failures() -> u64 {
+    INSTANCE.get_lock_misses()
+}
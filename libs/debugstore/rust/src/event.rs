@@ -0,0 +1,124 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::event_type::EventType;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::time::Instant;
+
+// The instant the first `Event` was constructed, used as a stable epoch so that `Instant`,
+// which has no meaningful absolute representation, can still be serialized into the `Display`
+// encoding as an offset.
+static PROCESS_START: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// A single debug event recorded by `DebugStore`.
+#[derive(Debug, Clone)]
+pub struct Event {
+    id: u64,
+    name: Option<String>,
+    timestamp: Instant,
+    event_type: EventType,
+    data: Vec<(String, String)>,
+    process_id: u32,
+    thread_id: i32,
+}
+
+impl Event {
+    /// Creates a new event with the given id, name, timestamp, type, and key/value data.
+    ///
+    /// The calling thread's process id and thread id are captured automatically so that events
+    /// recorded concurrently by different threads can be told apart and grouped.
+    pub fn new(
+        id: u64,
+        name: Option<String>,
+        timestamp: Instant,
+        event_type: EventType,
+        data: Vec<(String, String)>,
+    ) -> Self {
+        Lazy::force(&PROCESS_START);
+        Self {
+            id,
+            name,
+            timestamp,
+            event_type,
+            data,
+            process_id: std::process::id(),
+            // Safety: `gettid()` takes no arguments and always succeeds.
+            thread_id: unsafe { libc::gettid() },
+        }
+    }
+
+    /// The event's id, shared between a `DurationStart` and its matching `DurationEnd`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The event's name, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The instant at which the event was recorded.
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+
+    /// The event's type.
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    /// The event's key/value data.
+    pub fn data(&self) -> &[(String, String)] {
+        &self.data
+    }
+
+    /// The id of the process that recorded this event.
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// The id of the thread that recorded this event.
+    pub fn thread_id(&self) -> i32 {
+        self.thread_id
+    }
+
+    /// Microseconds elapsed between the first ever recorded event and this one.
+    ///
+    /// Used as the `Display` encoding's timestamp field, since `Instant` has no serializable
+    /// absolute value.
+    pub(super) fn micros_since_start(&self) -> u128 {
+        self.timestamp.saturating_duration_since(*PROCESS_START).as_micros()
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{},{}",
+            self.id,
+            self.event_type,
+            self.name.as_deref().unwrap_or(""),
+            self.micros_since_start(),
+            self.process_id,
+            self.thread_id
+        )?;
+        for (key, value) in &self.data {
+            write!(f, ";{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * Copyright (C) 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+
+/// The kind of a recorded debug event, determining how it is paired with other events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    /// The start of a duration event; paired with a later `DurationEnd` sharing the same id.
+    DurationStart,
+    /// The end of a duration event; paired with an earlier `DurationStart` sharing the same id.
+    DurationEnd,
+    /// An instantaneous event with no duration.
+    Point,
+}
+
+impl EventType {
+    /// Parses the single-letter code used by [`fmt::Display`] back into an `EventType`.
+    pub(crate) fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "B" => Some(EventType::DurationStart),
+            "E" => Some(EventType::DurationEnd),
+            "P" => Some(EventType::Point),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            EventType::DurationStart => "B",
+            EventType::DurationEnd => "E",
+            EventType::Point => "P",
+        };
+        write!(f, "{}", code)
+    }
+}